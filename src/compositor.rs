@@ -0,0 +1,174 @@
+use strum_macros::{Display, EnumString};
+
+use crate::{effects::RunningEffect, ClientType, Color, LedData};
+
+/// How a layer's colours are combined with the layers below it
+#[derive(Clone, Copy, Debug, PartialEq, Display, EnumString)]
+#[strum(serialize_all = "snake_case", ascii_case_insensitive)]
+pub enum BlendMode {
+    /// The layer replaces what is below it
+    Normal,
+    Add,
+    Multiply,
+    Screen,
+    /// The brighter of the two, per channel
+    Max,
+}
+
+impl BlendMode {
+    fn blend_channel(&self, below: u8, layer: u8) -> u8 {
+        let (below, layer) = (below as f32 / 255.0, layer as f32 / 255.0);
+
+        let blended = match self {
+            BlendMode::Normal => layer,
+            BlendMode::Add => below + layer,
+            BlendMode::Multiply => below * layer,
+            BlendMode::Screen => 1.0 - (1.0 - below) * (1.0 - layer),
+            BlendMode::Max => below.max(layer),
+        };
+
+        (blended.clamp(0.0, 1.0) * 255.0).round() as u8
+    }
+
+    /// Blends `layer` onto `below`, `opacity` fades between no change and the full blend
+    pub fn blend(&self, below: Color, layer: Color, opacity: f32) -> Color {
+        let channel = |below: u8, layer: u8| {
+            let blended = self.blend_channel(below, layer) as f32;
+            (below as f32 + (blended - below as f32) * opacity).round() as u8
+        };
+
+        Color::new(
+            channel(below.0, layer.0),
+            channel(below.1, layer.1),
+            channel(below.2, layer.2),
+        )
+    }
+}
+
+pub struct Layer {
+    pub client_type: ClientType,
+    pub effect: RunningEffect,
+    /// 0.0 - 1.0
+    pub opacity: f32,
+    pub blend: BlendMode,
+}
+
+/// An ordered stack of effects drawn on top of the scene's main effect, the first layer is the
+/// lowest
+#[derive(Default)]
+pub struct Compositor {
+    layers: Vec<Layer>,
+}
+
+impl Compositor {
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    /// Adds a layer on top of the others, returning its index
+    pub fn push(&mut self, layer: Layer) -> usize {
+        self.layers.push(layer);
+        self.layers.len() - 1
+    }
+
+    /// Removes a layer, the layers above it move down an index
+    pub fn remove(&mut self, index: usize) -> Option<Layer> {
+        (index < self.layers.len()).then(|| self.layers.remove(index))
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Layer> {
+        self.layers.get_mut(index)
+    }
+
+    /// Carries every layer on from where it was, see [`RunningEffect::resume`]
+    pub fn resume(&mut self) {
+        for layer in &mut self.layers {
+            layer.effect.resume();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.layers.clear();
+    }
+
+    /// The highest fps any layer wants
+    pub fn fps(&self) -> Option<f32> {
+        self.layers
+            .iter()
+            .map(|layer| layer.effect.get_config().fps)
+            .reduce(f32::max)
+    }
+
+    /// Renders every layer and blends them in order onto `data`
+    pub fn compose(&mut self, data: &mut LedData) {
+        for layer in &mut self.layers {
+            let frame = layer.effect.render();
+
+            for (below, above) in data.iter_mut().zip(frame) {
+                *below = layer.blend.blend(*below, above, layer.opacity);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channels(c: Color) -> (u8, u8, u8) {
+        (c.0, c.1, c.2)
+    }
+
+    #[test]
+    fn blends_channels() {
+        let below = Color::new(100, 200, 0);
+        let layer = Color::new(200, 100, 255);
+
+        assert_eq!(
+            channels(BlendMode::Normal.blend(below, layer, 1.0)),
+            (200, 100, 255)
+        );
+        assert_eq!(
+            channels(BlendMode::Add.blend(below, layer, 1.0)),
+            (255, 255, 255)
+        );
+        assert_eq!(
+            channels(BlendMode::Multiply.blend(below, layer, 1.0)),
+            (78, 78, 0)
+        );
+        assert_eq!(
+            channels(BlendMode::Screen.blend(below, layer, 1.0)),
+            (222, 222, 255)
+        );
+        assert_eq!(
+            channels(BlendMode::Max.blend(below, layer, 1.0)),
+            (200, 200, 255)
+        );
+    }
+
+    #[test]
+    fn applies_opacity() {
+        let below = Color::new(0, 100, 200);
+        let layer = Color::new(200, 100, 0);
+
+        assert_eq!(
+            channels(BlendMode::Normal.blend(below, layer, 0.0)),
+            (0, 100, 200)
+        );
+        assert_eq!(
+            channels(BlendMode::Normal.blend(below, layer, 0.5)),
+            (100, 100, 100)
+        );
+    }
+
+    #[test]
+    fn parses_modes() {
+        assert_eq!("screen".parse::<BlendMode>().unwrap(), BlendMode::Screen);
+        assert_eq!("Max".parse::<BlendMode>().unwrap(), BlendMode::Max);
+        assert!("overlay".parse::<BlendMode>().is_err());
+    }
+}
@@ -0,0 +1,1331 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::Duration,
+};
+
+use chrono::Weekday;
+use sacn_unofficial::packet::{
+    ACN_SDT_MULTICAST_PORT, E131_DEFAULT_PRIORITY, E131_MAX_MULTICAST_UNIVERSE, E131_MAX_PRIORITY,
+    E131_MIN_MULTICAST_UNIVERSE, E131_SOURCE_NAME_FIELD_LENGTH,
+};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    correction::NEUTRAL_TEMPERATURE,
+    recovery::RecoveryAction,
+    schedule::At,
+    sink::ChannelOrder,
+    transition::{Transition, TransitionKind},
+    ClientType,
+};
+
+const CONFIG_DIR: &str = "led-controller";
+const CONFIG_FILE: &str = "config.toml";
+
+/// The number of whole RGB pixels that fit in the 512 channels of a DMX universe
+const PIXELS_PER_UNIVERSE: usize = 170;
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Installs the config used by the rest of the daemon, only the first call has any effect
+pub fn init(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+/// The installed config, or the defaults if [`init`] has not been called
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("could not find a config directory for the current user")]
+    NoConfigDir,
+    #[error("failed to read config file {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("failed to parse config file {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("invalid value for `{field}`: {reason}")]
+    Invalid { field: &'static str, reason: String },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub strip: StripConfig,
+    #[serde(rename = "output")]
+    pub outputs: Vec<OutputConfig>,
+    pub correction: CorrectionConfig,
+    pub power: PowerConfig,
+    pub transition: TransitionConfig,
+    pub replay: ReplayConfig,
+    pub recovery: RecoveryConfig,
+    #[serde(rename = "playlist")]
+    pub playlists: BTreeMap<String, PlaylistConfig>,
+    pub schedule: ScheduleConfig,
+    pub stream: StreamConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            strip: StripConfig::default(),
+            outputs: vec![OutputConfig::Sacn(SacnConfig::default())],
+            correction: CorrectionConfig::default(),
+            power: PowerConfig::default(),
+            transition: TransitionConfig::default(),
+            replay: ReplayConfig::default(),
+            recovery: RecoveryConfig::default(),
+            playlists: BTreeMap::new(),
+            schedule: ScheduleConfig::default(),
+            stream: StreamConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StripConfig {
+    pub led_count: usize,
+}
+
+impl Default for StripConfig {
+    fn default() -> Self {
+        Self {
+            led_count: PIXELS_PER_UNIVERSE,
+        }
+    }
+}
+
+impl StripConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.led_count == 0 {
+            return Err(ConfigError::Invalid {
+                field: "strip.led_count",
+                reason: "must be at least 1".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Corrections applied to every frame before it is sent, the defaults leave frames unchanged
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorrectionConfig {
+    /// Most strips look right with a gamma between 2.2 and 2.8
+    pub gamma: f32,
+    /// Scales for the red, green and blue channels
+    pub white_balance: [f32; 3],
+    /// Colour temperature of white in kelvin
+    pub temperature: f32,
+}
+
+impl Default for CorrectionConfig {
+    fn default() -> Self {
+        Self {
+            gamma: 1.0,
+            white_balance: [1.0; 3],
+            temperature: NEUTRAL_TEMPERATURE,
+        }
+    }
+}
+
+impl CorrectionConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        if !(0.1..=5.0).contains(&self.gamma) {
+            return Err(ConfigError::Invalid {
+                field: "correction.gamma",
+                reason: format!("{} is outside of 0.1..=5.0", self.gamma),
+            });
+        }
+
+        if self
+            .white_balance
+            .iter()
+            .any(|scale| !(0.0..=1.0).contains(scale))
+        {
+            return Err(ConfigError::Invalid {
+                field: "correction.white_balance",
+                reason: "every channel must be between 0.0 and 1.0".to_string(),
+            });
+        }
+
+        if !(1000.0..=40000.0).contains(&self.temperature) {
+            return Err(ConfigError::Invalid {
+                field: "correction.temperature",
+                reason: format!("{} is outside of 1000..=40000", self.temperature),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PowerConfig {
+    /// Brightness the daemon starts with, 0.0 - 1.0
+    pub brightness: f32,
+    /// Current drawn by a single colour channel at full brightness
+    pub milliamps_per_channel: f32,
+    /// Current drawn by each led even when it is off
+    pub idle_milliamps_per_led: f32,
+    /// What the power supply can deliver, frames are scaled down to stay within it
+    pub budget_milliamps: Option<f32>,
+}
+
+impl Default for PowerConfig {
+    fn default() -> Self {
+        Self {
+            brightness: 1.0,
+            milliamps_per_channel: 20.0,
+            idle_milliamps_per_led: 1.0,
+            budget_milliamps: None,
+        }
+    }
+}
+
+impl PowerConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        if !(0.0..=1.0).contains(&self.brightness) {
+            return Err(ConfigError::Invalid {
+                field: "power.brightness",
+                reason: format!("{} is outside of 0.0..=1.0", self.brightness),
+            });
+        }
+
+        let currents = [
+            ("power.milliamps_per_channel", self.milliamps_per_channel),
+            ("power.idle_milliamps_per_led", self.idle_milliamps_per_led),
+            (
+                "power.budget_milliamps",
+                self.budget_milliamps.unwrap_or(0.0),
+            ),
+        ];
+        for (field, current) in currents {
+            if current < 0.0 {
+                return Err(ConfigError::Invalid {
+                    field,
+                    reason: "must not be negative".to_string(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The transition used when the effect is changed without picking one
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TransitionConfig {
+    pub kind: TransitionKind,
+    pub duration_ms: u64,
+}
+
+impl Default for TransitionConfig {
+    fn default() -> Self {
+        Self {
+            kind: TransitionKind::Crossfade,
+            duration_ms: 500,
+        }
+    }
+}
+
+impl TransitionConfig {
+    pub fn transition(&self) -> Transition {
+        Transition {
+            kind: self.kind,
+            duration: Duration::from_millis(self.duration_ms),
+        }
+    }
+}
+
+/// What happens when the current effect fails to render a frame
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecoveryConfig {
+    pub action: RecoveryAction,
+    /// Wait before the first attempt after an error, doubling after each further error
+    pub backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    /// Consecutive errors before switching to `fallback`, never if unset
+    pub fallback_after: Option<u32>,
+    pub fallback: ClientType,
+    /// How long an effect's update can take before it is treated as hung
+    pub timeout_ms: u64,
+}
+
+impl Default for RecoveryConfig {
+    fn default() -> Self {
+        Self {
+            action: RecoveryAction::Reinit,
+            backoff_ms: 100,
+            max_backoff_ms: 10_000,
+            fallback_after: None,
+            fallback: ClientType::RainbowEffect,
+            timeout_ms: 1000,
+        }
+    }
+}
+
+impl RecoveryConfig {
+    /// How long to wait after the given number of consecutive errors
+    pub fn backoff(&self, errors: u32) -> Duration {
+        let doublings = errors.saturating_sub(1).min(63);
+        let backoff = self.backoff_ms.saturating_mul(1 << doublings);
+        Duration::from_millis(backoff.min(self.max_backoff_ms))
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.max_backoff_ms < self.backoff_ms {
+            return Err(ConfigError::Invalid {
+                field: "recovery.max_backoff_ms",
+                reason: "must be at least recovery.backoff_ms".to_string(),
+            });
+        }
+
+        if self.timeout_ms == 0 {
+            return Err(ConfigError::Invalid {
+                field: "recovery.timeout_ms",
+                reason: "must be at least 1".to_string(),
+            });
+        }
+
+        if self.fallback_after == Some(0) {
+            return Err(ConfigError::Invalid {
+                field: "recovery.fallback_after",
+                reason: "must be at least 1".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// A named list of effects the daemon cycles through
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlaylistConfig {
+    /// Plays the entries in a random order, shuffled again each time round
+    pub shuffle: bool,
+    #[serde(rename = "entry")]
+    pub entries: Vec<PlaylistEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlaylistEntry {
+    pub effect: ClientType,
+    pub duration_s: u64,
+    /// Parameters set on the effect when it starts
+    #[serde(default)]
+    pub params: BTreeMap<String, toml::Value>,
+}
+
+impl PlaylistEntry {
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.duration_s)
+    }
+
+    /// The parameters as the strings [`crate::parameters::Parameters::set`] parses
+    pub fn params(&self) -> impl Iterator<Item = (&str, String)> {
+        self.params.iter().map(|(name, value)| {
+            let value = match value {
+                toml::Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            (name.as_str(), value)
+        })
+    }
+}
+
+impl PlaylistConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.entries.is_empty() {
+            return Err(ConfigError::Invalid {
+                field: "playlist.entry",
+                reason: "at least one entry is required".to_string(),
+            });
+        }
+
+        if self.entries.iter().any(|entry| entry.duration_s == 0) {
+            return Err(ConfigError::Invalid {
+                field: "playlist.entry.duration_s",
+                reason: "must be at least 1".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// The fastest pushed frames can be shown, well beyond what a strip can take
+const MAX_STREAM_FPS: f32 = 1000.0;
+
+/// Frames pushed by other programs with `PushFrame`
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StreamConfig {
+    /// How often pushed frames are shown, any pushed in between are dropped
+    pub fps: f32,
+    /// How long without a frame before going back to the effect that was running before
+    pub idle_timeout_ms: u64,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            fps: 60.0,
+            idle_timeout_ms: 5000,
+        }
+    }
+}
+
+impl StreamConfig {
+    pub fn idle_timeout(&self) -> Duration {
+        Duration::from_millis(self.idle_timeout_ms)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        // NaN fails both comparisons
+        if !(self.fps > 0.0 && self.fps <= MAX_STREAM_FPS) {
+            return Err(ConfigError::Invalid {
+                field: "stream.fps",
+                reason: format!("must be above 0 and at most {}", MAX_STREAM_FPS),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Rules that change the effect or brightness at set times of day
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScheduleConfig {
+    /// Where the strip is, in degrees north, needed by sunrise and sunset rules
+    pub latitude: Option<f64>,
+    /// In degrees east
+    pub longitude: Option<f64>,
+    #[serde(rename = "rule")]
+    pub rules: Vec<ScheduleRule>,
+}
+
+/// Takes effect at `at` and lasts until the next rule starts
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleRule {
+    /// Every day if empty
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    pub at: At,
+    pub effect: Option<ClientType>,
+    pub playlist: Option<String>,
+    pub brightness: Option<f32>,
+    /// Turns the strip off, any rule without it turns the strip back on
+    #[serde(default)]
+    pub blackout: bool,
+}
+
+impl ScheduleConfig {
+    fn validate(&self, playlists: &BTreeMap<String, PlaylistConfig>) -> Result<(), ConfigError> {
+        if let Some(latitude) = self.latitude {
+            if !(-90.0..=90.0).contains(&latitude) {
+                return Err(ConfigError::Invalid {
+                    field: "schedule.latitude",
+                    reason: format!("{} is outside of -90.0..=90.0", latitude),
+                });
+            }
+        }
+
+        if let Some(longitude) = self.longitude {
+            if !(-180.0..=180.0).contains(&longitude) {
+                return Err(ConfigError::Invalid {
+                    field: "schedule.longitude",
+                    reason: format!("{} is outside of -180.0..=180.0", longitude),
+                });
+            }
+        }
+
+        let located = self.latitude.is_some() && self.longitude.is_some();
+
+        for rule in &self.rules {
+            if rule.at.uses_sun() && !located {
+                return Err(ConfigError::Invalid {
+                    field: "schedule.rule.at",
+                    reason: "sunrise and sunset need schedule.latitude and schedule.longitude"
+                        .to_string(),
+                });
+            }
+
+            if rule.effect.is_some() && rule.playlist.is_some() {
+                return Err(ConfigError::Invalid {
+                    field: "schedule.rule.playlist",
+                    reason: "a rule can set an effect or a playlist, not both".to_string(),
+                });
+            }
+
+            if let Some(playlist) = &rule.playlist {
+                if !playlists.contains_key(playlist) {
+                    return Err(ConfigError::Invalid {
+                        field: "schedule.rule.playlist",
+                        reason: format!("unknown playlist {}", playlist),
+                    });
+                }
+            }
+
+            if let Some(brightness) = rule.brightness {
+                if !(0.0..=1.0).contains(&brightness) {
+                    return Err(ConfigError::Invalid {
+                        field: "schedule.rule.brightness",
+                        reason: format!("{} is outside of 0.0..=1.0", brightness),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A single place frames are sent to, every output receives every frame
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum OutputConfig {
+    Sacn(SacnConfig),
+    ArtNet(ArtNetConfig),
+    Wled(WledConfig),
+    Preview(PreviewConfig),
+    Record(RecordConfig),
+}
+
+impl OutputConfig {
+    fn validate(&self, led_count: usize) -> Result<(), ConfigError> {
+        match self {
+            OutputConfig::Sacn(config) => config.validate(led_count),
+            OutputConfig::ArtNet(config) => config.validate(led_count),
+            OutputConfig::Wled(config) => config.validate(led_count),
+            OutputConfig::Preview(config) => config.validate(),
+            OutputConfig::Record(_) => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SacnConfig {
+    /// Address of the receiver, packets are always sent to the E1.31 port
+    pub destination: IpAddr,
+    pub universe: u16,
+    pub source_name: String,
+    pub priority: u8,
+    /// Local address the source socket binds to
+    pub bind: SocketAddr,
+    pub channel_order: ChannelOrder,
+}
+
+impl Default for SacnConfig {
+    fn default() -> Self {
+        Self {
+            destination: IpAddr::V4(Ipv4Addr::new(192, 168, 1, 73)),
+            universe: 1,
+            source_name: "Source".to_string(),
+            priority: E131_DEFAULT_PRIORITY,
+            bind: SocketAddr::new(
+                IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                ACN_SDT_MULTICAST_PORT + 1,
+            ),
+            channel_order: ChannelOrder::brg(),
+        }
+    }
+}
+
+impl SacnConfig {
+    pub fn destination(&self) -> SocketAddr {
+        SocketAddr::new(self.destination, ACN_SDT_MULTICAST_PORT)
+    }
+
+    /// The consecutive universes needed to send `led_count` pixels, starting at `universe`
+    pub fn universes(&self, led_count: usize) -> Vec<u16> {
+        let count = led_count.div_ceil(self.channel_order.pixels_per_universe()) as u16;
+        (self.universe..self.universe.saturating_add(count)).collect()
+    }
+
+    fn validate(&self, led_count: usize) -> Result<(), ConfigError> {
+        let universes = led_count.div_ceil(self.channel_order.pixels_per_universe());
+        let last_universe = self.universe as usize + universes - 1;
+
+        if self.universe < E131_MIN_MULTICAST_UNIVERSE
+            || last_universe > E131_MAX_MULTICAST_UNIVERSE as usize
+        {
+            return Err(ConfigError::Invalid {
+                field: "output.universe",
+                reason: format!(
+                    "{}..={} is outside of {}..={}",
+                    self.universe,
+                    last_universe,
+                    E131_MIN_MULTICAST_UNIVERSE,
+                    E131_MAX_MULTICAST_UNIVERSE
+                ),
+            });
+        }
+
+        if self.priority > E131_MAX_PRIORITY {
+            return Err(ConfigError::Invalid {
+                field: "output.priority",
+                reason: format!(
+                    "{} is above the maximum of {}",
+                    self.priority, E131_MAX_PRIORITY
+                ),
+            });
+        }
+
+        // The source name field is null terminated
+        if self.source_name.is_empty() || self.source_name.len() >= E131_SOURCE_NAME_FIELD_LENGTH {
+            return Err(ConfigError::Invalid {
+                field: "output.source_name",
+                reason: format!(
+                    "must be between 1 and {} bytes long",
+                    E131_SOURCE_NAME_FIELD_LENGTH - 1
+                ),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// The UDP port Art-Net nodes listen on
+pub const ART_NET_PORT: u16 = 6454;
+
+/// The highest 15 bit Art-Net port-address
+const ART_NET_MAX_PORT_ADDRESS: usize = 0x7FFF;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArtNetConfig {
+    pub destination: IpAddr,
+    pub port: u16,
+    pub net: u8,
+    pub subnet: u8,
+    pub universe: u8,
+    /// Sends an ArtSync after every frame so nodes output all universes at once
+    pub sync: bool,
+    pub bind: SocketAddr,
+    pub channel_order: ChannelOrder,
+}
+
+impl Default for ArtNetConfig {
+    fn default() -> Self {
+        Self {
+            destination: IpAddr::V4(Ipv4Addr::BROADCAST),
+            port: ART_NET_PORT,
+            net: 0,
+            subnet: 0,
+            universe: 0,
+            sync: false,
+            bind: SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+            channel_order: ChannelOrder::rgb(),
+        }
+    }
+}
+
+impl ArtNetConfig {
+    pub fn destination(&self) -> SocketAddr {
+        SocketAddr::new(self.destination, self.port)
+    }
+
+    /// The 15 bit port-address made up of the net, subnet and universe
+    pub fn port_address(&self) -> u16 {
+        (self.net as u16) << 8 | (self.subnet as u16) << 4 | self.universe as u16
+    }
+
+    /// The consecutive port-addresses needed to send `led_count` pixels
+    pub fn port_addresses(&self, led_count: usize) -> Vec<u16> {
+        let count = led_count.div_ceil(self.channel_order.pixels_per_universe()) as u16;
+        (self.port_address()..self.port_address().saturating_add(count)).collect()
+    }
+
+    fn validate(&self, led_count: usize) -> Result<(), ConfigError> {
+        let limits = [
+            ("output.net", self.net, 127),
+            ("output.subnet", self.subnet, 15),
+            ("output.universe", self.universe, 15),
+        ];
+        for (field, value, max) in limits {
+            if value > max {
+                return Err(ConfigError::Invalid {
+                    field,
+                    reason: format!("{} is above the maximum of {}", value, max),
+                });
+            }
+        }
+
+        let last_address = self.port_address() as usize
+            + led_count.div_ceil(self.channel_order.pixels_per_universe())
+            - 1;
+        if last_address > ART_NET_MAX_PORT_ADDRESS {
+            return Err(ConfigError::Invalid {
+                field: "output.universe",
+                reason: format!(
+                    "the strip needs port-addresses up to {:#x}, above the maximum of {:#x}",
+                    last_address, ART_NET_MAX_PORT_ADDRESS
+                ),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// The port WLED listens on for its own realtime protocols
+pub const WLED_PORT: u16 = 21324;
+/// The port WLED listens on for DDP
+pub const DDP_PORT: u16 = 4048;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WledProtocol {
+    /// Index and colour per led, up to 255 leds
+    Warls,
+    /// Colours for the whole strip, up to 490 leds
+    Drgb,
+    /// Colours from a start index, long strips are split over several packets
+    Dnrgb,
+    /// Distributed Display Protocol
+    Ddp,
+}
+
+impl WledProtocol {
+    /// The most leds a single frame can address
+    pub fn max_leds(&self) -> Option<usize> {
+        match self {
+            WledProtocol::Warls => Some(255),
+            WledProtocol::Drgb => Some(490),
+            WledProtocol::Dnrgb => Some(u16::MAX as usize + 1),
+            WledProtocol::Ddp => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WledConfig {
+    pub destination: IpAddr,
+    pub protocol: WledProtocol,
+    /// Defaults to the port for the chosen protocol
+    pub port: Option<u16>,
+    /// Seconds WLED waits after the last packet before going back to its own effects, 255 waits
+    /// forever. Not sent with DDP
+    pub timeout: u8,
+    pub bind: SocketAddr,
+}
+
+impl Default for WledConfig {
+    fn default() -> Self {
+        Self {
+            destination: IpAddr::V4(Ipv4Addr::new(4, 3, 2, 1)),
+            protocol: WledProtocol::Dnrgb,
+            port: None,
+            timeout: 2,
+            bind: SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+        }
+    }
+}
+
+impl WledConfig {
+    pub fn destination(&self) -> SocketAddr {
+        let port = self.port.unwrap_or(match self.protocol {
+            WledProtocol::Ddp => DDP_PORT,
+            _ => WLED_PORT,
+        });
+        SocketAddr::new(self.destination, port)
+    }
+
+    fn validate(&self, led_count: usize) -> Result<(), ConfigError> {
+        if let Some(max_leds) = self.protocol.max_leds() {
+            if led_count > max_leds {
+                return Err(ConfigError::Invalid {
+                    field: "output.protocol",
+                    reason: format!(
+                        "{:?} supports at most {} leds, the strip has {}",
+                        self.protocol, max_leds, led_count
+                    ),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PreviewLayout {
+    /// Strips wider than the terminal continue on the next line
+    Wrap,
+    /// Strips wider than the terminal are averaged down to a single line
+    Downsample,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PreviewConfig {
+    /// Columns to draw in, defaults to `$COLUMNS` or 80
+    pub width: Option<usize>,
+    pub layout: PreviewLayout,
+    pub show_fps: bool,
+}
+
+impl Default for PreviewConfig {
+    fn default() -> Self {
+        Self {
+            width: None,
+            layout: PreviewLayout::Wrap,
+            show_fps: true,
+        }
+    }
+}
+
+impl PreviewConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.width == Some(0) {
+            return Err(ConfigError::Invalid {
+                field: "output.width",
+                reason: "must be at least 1".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// `$XDG_DATA_HOME/led-controller/recording.ledrec`, used by both recording and replaying
+fn default_recording_path() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_default()
+        .join(CONFIG_DIR)
+        .join("recording.ledrec")
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecordConfig {
+    /// Frames are appended if the file already exists
+    pub path: PathBuf,
+}
+
+impl Default for RecordConfig {
+    fn default() -> Self {
+        Self {
+            path: default_recording_path(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReplayConfig {
+    pub path: PathBuf,
+    /// Starts again from the beginning once the last frame has been shown
+    pub repeat: bool,
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        Self {
+            path: default_recording_path(),
+            repeat: true,
+        }
+    }
+}
+
+impl Config {
+    /// The config file location, `$XDG_CONFIG_HOME/led-controller/config.toml`
+    pub fn path() -> Result<PathBuf, ConfigError> {
+        dirs::config_dir()
+            .map(|dir| dir.join(CONFIG_DIR).join(CONFIG_FILE))
+            .ok_or(ConfigError::NoConfigDir)
+    }
+
+    /// Loads the config from the default location, falling back to the defaults if there is no
+    /// config file
+    pub fn load() -> Result<Self, ConfigError> {
+        let path = Self::path()?;
+
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        Self::load_from(&path)
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self, ConfigError> {
+        let contents = fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        let config: Self = toml::from_str(&contents).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        self.strip.validate()?;
+        self.correction.validate()?;
+        self.power.validate()?;
+        self.recovery.validate()?;
+
+        for playlist in self.playlists.values() {
+            playlist.validate()?;
+        }
+
+        self.schedule.validate(&self.playlists)?;
+        self.stream.validate()?;
+
+        if self.outputs.is_empty() {
+            return Err(ConfigError::Invalid {
+                field: "output",
+                reason: "at least one output is required".to_string(),
+            });
+        }
+
+        for output in &self.outputs {
+            output.validate(self.strip.led_count)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(contents: &str) -> Result<Config, ConfigError> {
+        let config: Config = toml::from_str(contents).map_err(|source| ConfigError::Parse {
+            path: PathBuf::new(),
+            source,
+        })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn sacn(output: &OutputConfig) -> &SacnConfig {
+        match output {
+            OutputConfig::Sacn(config) => config,
+            _ => panic!("expected an sACN output"),
+        }
+    }
+
+    fn art_net(output: &OutputConfig) -> &ArtNetConfig {
+        match output {
+            OutputConfig::ArtNet(config) => config,
+            _ => panic!("expected an Art-Net output"),
+        }
+    }
+
+    #[test]
+    fn empty_config_uses_defaults() {
+        let config = parse("").unwrap();
+        assert_eq!(config.outputs.len(), 1);
+        assert_eq!(sacn(&config.outputs[0]).universe, 1);
+        assert_eq!(sacn(&config.outputs[0]).source_name, "Source");
+    }
+
+    #[test]
+    fn parses_sacn_outputs() {
+        let config = parse(
+            r#"
+            [[output]]
+            type = "sacn"
+            destination = "10.0.0.5"
+            universe = 4
+            source_name = "Desk"
+            priority = 150
+            bind = "0.0.0.0:6000"
+
+            [[output]]
+            type = "sacn"
+            destination = "10.0.0.6"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.outputs.len(), 2);
+
+        let first = sacn(&config.outputs[0]);
+        assert_eq!(
+            first.destination(),
+            "10.0.0.5:5568".parse::<SocketAddr>().unwrap()
+        );
+        assert_eq!(first.universe, 4);
+        assert_eq!(first.priority, 150);
+        assert_eq!(first.bind.port(), 6000);
+
+        assert_eq!(sacn(&config.outputs[1]).universe, 1);
+    }
+
+    #[test]
+    fn parses_art_net_outputs() {
+        let config = parse(
+            r#"
+            [strip]
+            led_count = 300
+
+            [[output]]
+            type = "artnet"
+            destination = "10.0.0.7"
+            net = 1
+            subnet = 2
+            universe = 15
+            sync = true
+            "#,
+        )
+        .unwrap();
+
+        let output = art_net(&config.outputs[0]);
+        assert_eq!(output.destination().port(), ART_NET_PORT);
+        assert!(output.sync);
+        assert_eq!(output.port_address(), 0x12F);
+        assert_eq!(output.port_addresses(300), vec![0x12F, 0x130]);
+        assert_eq!(output.channel_order, ChannelOrder::rgb());
+    }
+
+    #[test]
+    fn parses_wled_outputs() {
+        let config = parse(
+            r#"
+            [[output]]
+            type = "wled"
+            destination = "10.0.0.8"
+            protocol = "ddp"
+
+            [[output]]
+            type = "wled"
+            destination = "10.0.0.9"
+            protocol = "drgb"
+            port = 1234
+            timeout = 255
+            "#,
+        )
+        .unwrap();
+
+        let OutputConfig::Wled(ddp) = &config.outputs[0] else {
+            panic!("expected a WLED output");
+        };
+        assert_eq!(ddp.destination().port(), DDP_PORT);
+
+        let OutputConfig::Wled(drgb) = &config.outputs[1] else {
+            panic!("expected a WLED output");
+        };
+        assert_eq!(drgb.protocol, WledProtocol::Drgb);
+        assert_eq!(drgb.destination().port(), 1234);
+        assert_eq!(drgb.timeout, 255);
+    }
+
+    #[test]
+    fn parses_preview_outputs() {
+        let config = parse("[[output]]\ntype = \"preview\"\nlayout = \"downsample\"").unwrap();
+
+        let OutputConfig::Preview(preview) = &config.outputs[0] else {
+            panic!("expected a preview output");
+        };
+        assert_eq!(preview.layout, PreviewLayout::Downsample);
+        assert!(preview.show_fps);
+    }
+
+    #[test]
+    fn parses_recording_paths() {
+        let config = parse(
+            r#"
+            [[output]]
+            type = "record"
+            path = "/tmp/glitch.ledrec"
+
+            [replay]
+            path = "/tmp/glitch.ledrec"
+            repeat = false
+            "#,
+        )
+        .unwrap();
+
+        let OutputConfig::Record(record) = &config.outputs[0] else {
+            panic!("expected a record output");
+        };
+        assert_eq!(record.path, config.replay.path);
+        assert!(!config.replay.repeat);
+    }
+
+    #[test]
+    fn parses_transition() {
+        let config =
+            parse("[transition]\nkind = \"fade_through_black\"\nduration_ms = 2000").unwrap();
+
+        assert_eq!(
+            config.transition.transition(),
+            Transition {
+                kind: TransitionKind::FadeThroughBlack,
+                duration: Duration::from_secs(2),
+            }
+        );
+        assert!(parse("[transition]\nkind = \"dissolve\"").is_err());
+    }
+
+    #[test]
+    fn parses_recovery() {
+        let config = parse(
+            "[recovery]\naction = \"retry\"\nbackoff_ms = 250\nmax_backoff_ms = 1000\nfallback_after = 3\nfallback = \"RandomEffect\"",
+        )
+        .unwrap();
+
+        assert_eq!(config.recovery.action, RecoveryAction::Retry);
+        assert_eq!(config.recovery.fallback_after, Some(3));
+        assert_eq!(config.recovery.fallback, ClientType::RandomEffect);
+
+        let backoffs: Vec<_> = (1..=4)
+            .map(|errors| config.recovery.backoff(errors))
+            .collect();
+        assert_eq!(backoffs, [250, 500, 1000, 1000].map(Duration::from_millis));
+    }
+
+    #[test]
+    fn parses_playlists() {
+        let config = parse(
+            r##"
+            [playlist.evening]
+            shuffle = true
+
+            [[playlist.evening.entry]]
+            effect = "RainbowEffect"
+            duration_s = 300
+            params = { speed = 50.0 }
+
+            [[playlist.evening.entry]]
+            effect = "CodingEffect"
+            duration_s = 30
+            params = { insert = "#00ff00" }
+            "##,
+        )
+        .unwrap();
+
+        let playlist = &config.playlists["evening"];
+        assert!(playlist.shuffle);
+        assert_eq!(playlist.entries[0].effect, ClientType::RainbowEffect);
+        assert_eq!(playlist.entries[0].duration(), Duration::from_secs(300));
+
+        let params: Vec<_> = playlist.entries[0].params().collect();
+        assert_eq!(params, [("speed", "50.0".to_string())]);
+        let params: Vec<_> = playlist.entries[1].params().collect();
+        assert_eq!(params, [("insert", "#00ff00".to_string())]);
+    }
+
+    #[test]
+    fn parses_schedule() {
+        let config = parse(
+            r#"
+            [schedule]
+            latitude = 51.5
+            longitude = -0.13
+
+            [[schedule.rule]]
+            days = ["mon", "Friday"]
+            at = "sunset-30"
+            brightness = 0.4
+
+            [[schedule.rule]]
+            at = "23:00"
+            blackout = true
+            "#,
+        )
+        .unwrap();
+
+        let rules = &config.schedule.rules;
+        assert_eq!(rules[0].days, [Weekday::Mon, Weekday::Fri]);
+        assert_eq!(rules[0].at, At::Sunset(-30));
+        assert_eq!(rules[0].brightness, Some(0.4));
+        assert!(rules[1].days.is_empty());
+        assert!(rules[1].blackout);
+    }
+
+    #[test]
+    fn splits_strip_across_universes() {
+        let config =
+            parse("[strip]\nled_count = 600\n[[output]]\ntype = \"sacn\"\nuniverse = 3").unwrap();
+        let output = sacn(&config.outputs[0]);
+        assert_eq!(output.universes(config.strip.led_count), vec![3, 4, 5, 6]);
+        assert_eq!(output.universes(170), vec![3]);
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        assert!(matches!(
+            parse("[[output]]\ntype = \"sacn\"\nuniverse = 0"),
+            Err(ConfigError::Invalid {
+                field: "output.universe",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("[[output]]\ntype = \"sacn\"\npriority = 201"),
+            Err(ConfigError::Invalid {
+                field: "output.priority",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("[strip]\nled_count = 0"),
+            Err(ConfigError::Invalid {
+                field: "strip.led_count",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("[strip]\nled_count = 600\n[[output]]\ntype = \"sacn\"\nuniverse = 63998"),
+            Err(ConfigError::Invalid {
+                field: "output.universe",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("[[output]]\ntype = \"artnet\"\nsubnet = 16"),
+            Err(ConfigError::Invalid {
+                field: "output.subnet",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("[strip]\nled_count = 340\n[[output]]\ntype = \"artnet\"\nnet = 127\nsubnet = 15\nuniverse = 15"),
+            Err(ConfigError::Invalid {
+                field: "output.universe",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("[strip]\nled_count = 300\n[[output]]\ntype = \"wled\"\nprotocol = \"warls\""),
+            Err(ConfigError::Invalid {
+                field: "output.protocol",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("[[output]]\ntype = \"sacn\"\nchannel_order = \"rgbx\""),
+            Err(ConfigError::Parse { .. })
+        ));
+        assert!(matches!(
+            parse("[correction]\ngamma = 0"),
+            Err(ConfigError::Invalid {
+                field: "correction.gamma",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("[correction]\nwhite_balance = [1.0, 1.2, 1.0]"),
+            Err(ConfigError::Invalid {
+                field: "correction.white_balance",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("[power]\nbrightness = 1.5"),
+            Err(ConfigError::Invalid {
+                field: "power.brightness",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("[power]\nbudget_milliamps = -1.0"),
+            Err(ConfigError::Invalid {
+                field: "power.budget_milliamps",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("[recovery]\nbackoff_ms = 1000\nmax_backoff_ms = 500"),
+            Err(ConfigError::Invalid {
+                field: "recovery.max_backoff_ms",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("[stream]\nfps = 1e10"),
+            Err(ConfigError::Invalid {
+                field: "stream.fps",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("[stream]\nfps = nan"),
+            Err(ConfigError::Invalid {
+                field: "stream.fps",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("[playlist.empty]\nshuffle = true"),
+            Err(ConfigError::Invalid {
+                field: "playlist.entry",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("[[playlist.short.entry]]\neffect = \"RandomEffect\"\nduration_s = 0"),
+            Err(ConfigError::Invalid {
+                field: "playlist.entry.duration_s",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("[[schedule.rule]]\nat = \"sunrise\"\nbrightness = 1.0"),
+            Err(ConfigError::Invalid {
+                field: "schedule.rule.at",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("[[schedule.rule]]\nat = \"20:00\"\nplaylist = \"evening\""),
+            Err(ConfigError::Invalid {
+                field: "schedule.rule.playlist",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("[[schedule.rule]]\nat = \"8pm\""),
+            Err(ConfigError::Parse { .. })
+        ));
+        assert!(matches!(
+            parse("output = []"),
+            Err(ConfigError::Invalid {
+                field: "output",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("[[output]]\ntype = \"sacn\"\ndestination = \"not an ip\""),
+            Err(ConfigError::Parse { .. })
+        ));
+        assert!(matches!(
+            parse("[[output]]\ntype = \"sacn\"\nunknown = 1"),
+            Err(ConfigError::Parse { .. })
+        ));
+    }
+}
@@ -9,6 +9,29 @@ use zbus::{dbus_proxy, Connection};
 
 trait Controller {
     async fn set_effect(&self, client_type: ClientType) -> zbus::Result<()>;
+    async fn set_effect_with_transition(
+        &self,
+        client_type: ClientType,
+        transition: &str,
+        duration_ms: u32,
+    ) -> zbus::Result<()>;
+    async fn add_layer(
+        &self,
+        client_type: ClientType,
+        opacity: f64,
+        blend: &str,
+    ) -> zbus::Result<u32>;
+    async fn remove_layer(&self, index: u32) -> zbus::Result<()>;
+    async fn clear_layers(&self) -> zbus::Result<()>;
+    async fn set_parameter(&self, name: &str, value: &str) -> zbus::Result<()>;
+    async fn play_playlist(&self, name: &str) -> zbus::Result<()>;
+    async fn next(&self) -> zbus::Result<()>;
+    async fn previous(&self) -> zbus::Result<()>;
+    async fn pause(&self) -> zbus::Result<()>;
+    async fn freeze(&self) -> zbus::Result<()>;
+    async fn blackout(&self) -> zbus::Result<()>;
+    async fn resume(&self) -> zbus::Result<()>;
+    async fn set_brightness(&self, brightness: f64) -> zbus::Result<()>;
     async fn end_daemon(&self) -> zbus::Result<()>;
 }
 
@@ -19,7 +42,87 @@ pub async fn controller(args: Args) -> zbus::Result<()> {
 
     if let Some(client_type) = args.effect {
         println!("Setting client type to {:?}", client_type);
-        proxy.set_effect(client_type).await?;
+        match args.transition {
+            Some(transition) => {
+                proxy
+                    .set_effect_with_transition(
+                        client_type,
+                        &transition.to_string(),
+                        args.transition_duration,
+                    )
+                    .await?
+            }
+            None => proxy.set_effect(client_type).await?,
+        }
+    }
+
+    if let Some(name) = &args.play_playlist {
+        println!("Playing playlist {}", name);
+        proxy.play_playlist(name).await?;
+    }
+
+    if args.next {
+        proxy.next().await?;
+    }
+
+    if args.previous {
+        proxy.previous().await?;
+    }
+
+    for param in &args.params {
+        let Some((name, value)) = param.split_once('=') else {
+            println!("Expected key=value, got {}", param);
+            continue;
+        };
+
+        println!("Setting {} to {}", name, value);
+        proxy.set_parameter(name, value).await?;
+    }
+
+    if args.clear_layers {
+        println!("Clearing layers");
+        proxy.clear_layers().await?;
+    }
+
+    if let Some(index) = args.remove_layer {
+        println!("Removing layer {}", index);
+        proxy.remove_layer(index).await?;
+    }
+
+    if let Some(client_type) = args.add_layer {
+        let index = proxy
+            .add_layer(
+                client_type,
+                args.layer_opacity,
+                &args.layer_blend.to_string(),
+            )
+            .await?;
+        println!("Added {:?} as layer {}", client_type, index);
+    }
+
+    if args.resume {
+        println!("Resuming");
+        proxy.resume().await?;
+    }
+
+    if args.pause {
+        println!("Pausing");
+        proxy.pause().await?;
+    }
+
+    if args.freeze {
+        println!("Freezing the current frame");
+        proxy.freeze().await?;
+    }
+
+    if args.blackout {
+        println!("Turning the strip off");
+        proxy.blackout().await?;
+    }
+
+    if let Some(brightness) = args.brightness {
+        println!("Setting brightness to {}", brightness);
+        proxy.set_brightness(brightness).await?;
     }
 
     if args.kill {
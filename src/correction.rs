@@ -0,0 +1,116 @@
+use crate::{config::CorrectionConfig, LedData};
+
+/// The colour temperature that leaves colours unchanged
+pub const NEUTRAL_TEMPERATURE: f32 = 6500.0;
+
+/// Corrects frames for the strip's response before they are sent, a lookup table is built per
+/// channel so applying it is cheap
+pub struct Correction {
+    tables: [[u8; 256]; 3],
+}
+
+impl Correction {
+    pub fn new(config: &CorrectionConfig) -> Self {
+        let temperature = temperature_scale(config.temperature);
+
+        let mut tables = [[0; 256]; 3];
+        for (channel, table) in tables.iter_mut().enumerate() {
+            let scale = config.white_balance[channel] * temperature[channel];
+
+            for (value, corrected) in table.iter_mut().enumerate() {
+                let linear = value as f32 / 255.0 * scale;
+                *corrected = (linear.powf(config.gamma) * 255.0)
+                    .round()
+                    .clamp(0.0, 255.0) as u8;
+            }
+        }
+
+        Self { tables }
+    }
+
+    pub fn apply(&self, data: &mut LedData) {
+        for c in data.iter_mut() {
+            c.0 = self.tables[0][c.0 as usize];
+            c.1 = self.tables[1][c.1 as usize];
+            c.2 = self.tables[2][c.2 as usize];
+        }
+    }
+}
+
+/// How much each channel is scaled to tint white to `kelvin`, relative to a neutral white
+fn temperature_scale(kelvin: f32) -> [f32; 3] {
+    let target = blackbody(kelvin);
+    let neutral = blackbody(NEUTRAL_TEMPERATURE);
+
+    [
+        target[0] / neutral[0],
+        target[1] / neutral[1],
+        target[2] / neutral[2],
+    ]
+}
+
+/// Approximate RGB colour of a black body at `kelvin`, from Tanner Helland's fit of the
+/// blackbody curve
+fn blackbody(kelvin: f32) -> [f32; 3] {
+    let t = kelvin / 100.0;
+
+    let r = if t <= 66.0 {
+        255.0
+    } else {
+        329.698_73 * (t - 60.0).powf(-0.133_204_76)
+    };
+
+    let g = if t <= 66.0 {
+        99.470_8 * t.ln() - 161.119_57
+    } else {
+        288.122_16 * (t - 60.0).powf(-0.075_514_85)
+    };
+
+    let b = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.517_73 * (t - 10.0).ln() - 305.044_8
+    };
+
+    [r, g, b].map(|channel| channel.clamp(0.0, 255.0) / 255.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+
+    #[test]
+    fn default_is_unchanged() {
+        let correction = Correction::new(&CorrectionConfig::default());
+        let mut data = vec![Color::new(0, 100, 255)];
+
+        correction.apply(&mut data);
+
+        assert_eq!((data[0].0, data[0].1, data[0].2), (0, 100, 255));
+    }
+
+    #[test]
+    fn applies_gamma_and_white_balance() {
+        let correction = Correction::new(&CorrectionConfig {
+            gamma: 2.0,
+            white_balance: [1.0, 1.0, 0.5],
+            ..CorrectionConfig::default()
+        });
+        let mut data = vec![Color::new(128, 255, 255)];
+
+        correction.apply(&mut data);
+
+        assert_eq!((data[0].0, data[0].1, data[0].2), (64, 255, 64));
+    }
+
+    #[test]
+    fn warm_temperatures_reduce_blue() {
+        let scale = temperature_scale(3000.0);
+
+        assert!(scale[0] >= 1.0);
+        assert!(scale[2] < scale[1] && scale[1] < 1.0);
+    }
+}
@@ -1,26 +1,52 @@
 use std::{
+    collections::HashMap,
     error::Error,
-    net::{IpAddr, SocketAddr},
-    process,
+    future, process,
     sync::{Arc, Mutex},
-    thread,
+    time::{Duration, Instant},
 };
 
-use crate::ClientType;
-use crate::{check_and_mark_running, Args, Effect, LedData, LED_SIZE, UNIVERSE};
-use crate::{effects::test::TestEffect, Color};
-use sacn_unofficial::{packet::ACN_SDT_MULTICAST_PORT, source::SacnSource};
-use zbus::{dbus_interface, ConnectionBuilder};
+use anyhow::{anyhow, Context};
+
+use crate::compositor::{BlendMode, Layer};
+use crate::config::{self, Config};
+use crate::correction::Correction;
+use crate::effects::RunningEffect;
+use crate::overlay::{Overlay, OverlayKind};
+use crate::parameters::parse_color;
+use crate::playlist::{self, Playlist};
+use crate::render::{RenderLoop, RenderStats};
+use crate::scene::Scene;
+use crate::schedule::Scheduler;
+use crate::sink::{self, Sink};
+use crate::transition::{Transition, TransitionKind};
+use crate::{check_and_mark_running, Args, Effect};
+use crate::{ClientType, Color};
+use zbus::names::InterfaceName;
+use zbus::zvariant::Value;
+use zbus::{dbus_interface, ConnectionBuilder, DBusError, SignalContext};
+
+const PATH: &str = "/dev/rugmj/LedController";
+/// Must match the name given to `dbus_interface`
+const INTERFACE: &str = "dev.rugmj.LedController1";
+
+/// How often the daemon's state is checked for changes to signal
+const STATE_INTERVAL: Duration = Duration::from_millis(250);
 
 async fn create_dbus_connection(
-    effect: &Arc<Mutex<Box<dyn Effect + Send>>>,
+    scene: &Arc<Mutex<Scene>>,
+    brightness: &Arc<Mutex<f32>>,
+    stats: &Arc<Mutex<RenderStats>>,
 ) -> Result<zbus::Connection, Box<dyn Error>> {
     let bus_interface = BusInterface {
-        effect: Arc::clone(effect),
+        scene: Arc::clone(scene),
+        brightness: Arc::clone(brightness),
+        stats: Arc::clone(stats),
+        runtime: tokio::runtime::Handle::current(),
     };
     Ok(ConnectionBuilder::session()?
         .name("dev.rugmj.LedController")?
-        .serve_at("/dev/rugmj/LedController", bus_interface)?
+        .serve_at(PATH, bus_interface)?
         .build()
         .await?)
 }
@@ -33,100 +59,694 @@ pub async fn daemon(args: Args) {
         panic!("Another instance is already running");
     }
 
-    let effect = create_effect(&args);
-    let effect = Arc::new(Mutex::new(effect));
+    match Config::load() {
+        Ok(config) => config::init(config),
+        Err(err) => {
+            println!("Error loading config: {}", err);
+            process::exit(1);
+        }
+    };
+    let config = config::get();
 
-    let _conn = create_dbus_connection(&effect).await.unwrap();
+    let sinks = match create_sinks(config) {
+        Ok(sinks) => sinks,
+        Err(err) => {
+            println!("Error setting up outputs: {}", err);
+            process::exit(1);
+        }
+    };
 
-    let (mut src, dst_ip) = setup_sacn();
-    loop {
-        let (config, data) = {
-            let mut effect = effect.lock().unwrap();
-            (
-                effect.get_config(),
-                effect.update().unwrap_or(Some([Color::BLACK; LED_SIZE])),
-            )
-        };
+    let client_type = initial_client_type(&args);
+    let effect = match client_type.into_effect() {
+        Ok(effect) => effect,
+        Err(err) => {
+            println!("Error starting effect: {:#}", err);
+            process::exit(1);
+        }
+    };
+    let mut effect = RunningEffect::new(effect);
+    for param in &args.params {
+        if let Err(err) = set_parameter(&mut effect, param) {
+            println!("Error setting parameter {}: {}", param, err);
+            process::exit(1);
+        }
+    }
 
-        if let Some(data) = data {
-            if let Err(err) = send_data(&mut src, dst_ip, &data) {
-                println!("Error: {:?}", err);
+    let mut scene = Scene::new(client_type, effect);
+
+    if let Some(name) = &args.play_playlist {
+        match start_playlist(name) {
+            Ok((playlist, effect)) => scene.play_playlist(playlist, effect),
+            Err(err) => {
+                println!("Error playing playlist {}: {:#}", name, err);
+                process::exit(1);
             }
         }
+    }
+    let scene = Arc::new(Mutex::new(scene));
+
+    let brightness = args
+        .brightness
+        .map(|brightness| brightness.clamp(0.0, 1.0) as f32)
+        .unwrap_or(config.power.brightness);
+    let brightness = Arc::new(Mutex::new(brightness));
 
-        thread::sleep(config.delay);
+    let stats = Arc::new(Mutex::new(RenderStats::default()));
+
+    let conn = create_dbus_connection(&scene, &brightness, &stats)
+        .await
+        .unwrap();
+    let signal_conn = conn.clone();
+    let (signal_scene, signal_brightness) = (Arc::clone(&scene), Arc::clone(&brightness));
+    tokio::spawn(async move {
+        if let Err(err) = emit_changes(signal_conn, signal_scene, signal_brightness).await {
+            println!("Error emitting signals: {}", err);
+        }
+    });
+
+    RenderLoop {
+        scene,
+        brightness,
+        stats,
+        sinks,
+        correction: Correction::new(&config.correction),
+        scheduler: Scheduler::new(),
     }
+    .spawn();
+
+    // The render loop runs until the process exits, the connection has to outlive it
+    future::pending::<()>().await;
 }
 
-fn create_effect(args: &Args) -> Box<dyn Effect + Send> {
-    match &args.effect {
-        Some(client_type) => (*client_type).into_effect(),
+fn initial_client_type(args: &Args) -> ClientType {
+    match args.effect {
+        Some(client_type) => client_type,
         None => {
             if args.test {
-                Box::new(TestEffect::new())
+                ClientType::TestEffect
             } else {
-                ClientType::RainbowEffect.into_effect()
+                ClientType::RainbowEffect
             }
         }
     }
 }
 
-#[derive(Debug)]
-enum SendDataErr {
-    IncorrectLength,
-    SacnError,
+/// Sets a parameter from a `key=value` argument
+fn set_parameter(effect: &mut RunningEffect, param: &str) -> Result<(), Box<dyn Error>> {
+    let (name, value) = param.split_once('=').ok_or("expected key=value")?;
+    Ok(effect.set_parameter(name, value)?)
+}
+
+/// Creates a playlist from the config along with the effect for its first entry
+fn start_playlist(name: &str) -> anyhow::Result<(Playlist, RunningEffect)> {
+    let config = config::get()
+        .playlists
+        .get(name)
+        .with_context(|| format!("unknown playlist {}", name))?;
+
+    let playlist = Playlist::new(name, config, Instant::now());
+    let effect = playlist::start_entry(name, playlist.current())?;
+    Ok((playlist, effect))
+}
+
+/// The parts of the daemon's state that are signalled when they change
+#[derive(Clone, PartialEq)]
+struct State {
+    effect: ClientType,
+    brightness: f32,
+    paused: bool,
+    frozen: bool,
+    blackout: bool,
+    last_error: Option<String>,
 }
 
-fn send_data(src: &mut SacnSource, dst_ip: SocketAddr, data: &LedData) -> Result<(), SendDataErr> {
-    if data.len() != LED_SIZE {
-        return Err(SendDataErr::IncorrectLength);
+impl State {
+    fn new(scene: &Mutex<Scene>, brightness: &Mutex<f32>) -> Self {
+        let scene = scene.lock().unwrap();
+        State {
+            effect: scene.client_type(),
+            brightness: *brightness.lock().unwrap(),
+            paused: scene.is_paused(),
+            frozen: scene.is_frozen(),
+            blackout: scene.is_blackout(),
+            last_error: scene.recovery().last_error().map(str::to_string),
+        }
     }
-    let data = data
-        .iter()
-        .flat_map(|&c| vec![c.2, c.0, c.1])
-        .collect::<Vec<u8>>();
-    let data_slice = data.as_slice();
 
-    if src
-        .send(&[UNIVERSE], data_slice, None, Some(dst_ip), None)
-        .is_err()
-    {
-        return Err(SendDataErr::SacnError);
+    /// Takes the state on a blocking thread, the scene stays locked while the render thread
+    /// updates the effects
+    async fn snapshot(scene: &Arc<Mutex<Scene>>, brightness: &Arc<Mutex<f32>>) -> Option<Self> {
+        let (scene, brightness) = (Arc::clone(scene), Arc::clone(brightness));
+        tokio::task::spawn_blocking(move || State::new(&scene, &brightness))
+            .await
+            .ok()
     }
+}
+
+/// Emits PropertiesChanged and EffectChanged. The state can be changed from the render thread,
+/// by a playlist, the schedule or an effect failing, so it is polled rather than signalled where
+/// it changes
+async fn emit_changes(
+    conn: zbus::Connection,
+    scene: Arc<Mutex<Scene>>,
+    brightness: Arc<Mutex<f32>>,
+) -> zbus::Result<()> {
+    let ctxt = SignalContext::new(&conn, PATH)?;
 
-    Ok(())
+    let mut last = None;
+    loop {
+        let Some(state) = State::snapshot(&scene, &brightness).await else {
+            println!("Error reading the daemon state");
+            tokio::time::sleep(STATE_INTERVAL).await;
+            continue;
+        };
+
+        if let Some(last) = last.as_ref().filter(|last| **last != state) {
+            if let Err(err) = emit(&ctxt, &state, last).await {
+                println!("Error emitting signals: {}", err);
+            }
+        }
+
+        last = Some(state);
+        tokio::time::sleep(STATE_INTERVAL).await;
+    }
 }
 
-fn setup_sacn() -> (SacnSource, SocketAddr) {
-    let destination_address = SocketAddr::new(
-        IpAddr::V4("192.168.1.73".parse().unwrap()),
-        ACN_SDT_MULTICAST_PORT,
-    );
-    let dst_ip = destination_address;
-    let local_addr = SocketAddr::new(
-        IpAddr::V4("0.0.0.0".parse().unwrap()),
-        ACN_SDT_MULTICAST_PORT + 1,
-    );
+/// Signals what has changed between two states, using the snapshot rather than the interface so
+/// nothing is locked while the signals are sent
+async fn emit(ctxt: &SignalContext<'_>, state: &State, last: &State) -> zbus::Result<()> {
+    if state.effect != last.effect {
+        BusInterface::effect_changed(ctxt, &state.effect.to_string()).await?;
+    }
+
+    let mut changed: HashMap<&str, Value> = HashMap::new();
+    if state.effect != last.effect {
+        changed.insert("CurrentEffect", state.effect.to_string().into());
+    }
+    if state.brightness != last.brightness {
+        changed.insert("Brightness", (state.brightness as f64).into());
+    }
+    if state.paused != last.paused {
+        changed.insert("Paused", state.paused.into());
+    }
+    if state.frozen != last.frozen {
+        changed.insert("Frozen", state.frozen.into());
+    }
+    if state.blackout != last.blackout {
+        changed.insert("BlackedOut", state.blackout.into());
+    }
+    if state.last_error != last.last_error {
+        let last_error = state.last_error.clone().unwrap_or_default();
+        changed.insert("LastError", last_error.into());
+    }
 
-    let mut src = SacnSource::with_ip("Source", local_addr).unwrap();
+    let changed: HashMap<&str, &Value> =
+        changed.iter().map(|(name, value)| (*name, value)).collect();
+    zbus::fdo::Properties::properties_changed(
+        ctxt,
+        InterfaceName::from_static_str_unchecked(INTERFACE),
+        &changed,
+        &[],
+    )
+    .await
+}
 
-    src.register_universe(UNIVERSE).unwrap();
+fn create_sinks(config: &Config) -> Result<Vec<Box<dyn Sink + Send>>, sink::SinkError> {
+    config.outputs.iter().map(sink::create_sink).collect()
+}
 
-    (src, dst_ip)
+/// Errors with their own D-Bus names so callers can tell them apart
+#[derive(DBusError, Debug)]
+#[dbus_error(prefix = "dev.rugmj.LedController1.Error")]
+enum ControllerError {
+    #[dbus_error(zbus_error)]
+    ZBus(zbus::Error),
+    /// No effect has the name, the message suggests close ones
+    UnknownEffect(String),
 }
 
 struct BusInterface {
-    effect: Arc<Mutex<Box<dyn Effect + Send>>>,
+    scene: Arc<Mutex<Scene>>,
+    brightness: Arc<Mutex<f32>>,
+    stats: Arc<Mutex<RenderStats>>,
+    /// zbus runs the interface on its own executor, blocking work is handed to tokio through this
+    runtime: tokio::runtime::Handle,
 }
 
 #[dbus_interface(name = "dev.rugmj.LedController1")]
 impl BusInterface {
-    fn set_effect(&mut self, new_client_type: ClientType) {
-        let mut effect = self.effect.lock().unwrap();
-        *effect = new_client_type.into_effect();
+    fn set_effect(&mut self, new_client_type: ClientType) -> zbus::fdo::Result<()> {
+        let transition = config::get().transition.transition();
+        self.change_effect(new_client_type, transition)
+    }
+
+    /// Sets the effect by a name like `rainbow` or `typing-ripple`, close typos are accepted
+    fn set_effect_by_name(&mut self, name: &str) -> Result<(), ControllerError> {
+        let client_type = name
+            .parse::<ClientType>()
+            .map_err(|err| ControllerError::UnknownEffect(err.to_string()))?;
+
+        let transition = config::get().transition.transition();
+        self.change_effect(client_type, transition)
+            .map_err(|err| ControllerError::ZBus(err.into()))
+    }
+
+    /// Sets the effect with a transition other than the configured one
+    fn set_effect_with_transition(
+        &mut self,
+        new_client_type: ClientType,
+        transition: &str,
+        duration_ms: u32,
+    ) -> zbus::fdo::Result<()> {
+        let kind = transition.parse::<TransitionKind>().map_err(|_| {
+            zbus::fdo::Error::InvalidArgs(format!("Unknown transition {}", transition))
+        })?;
+
+        self.change_effect(
+            new_client_type,
+            Transition {
+                kind,
+                duration: Duration::from_millis(duration_ms as u64),
+            },
+        )
+    }
+
+    /// Adds an effect on top of the others, returning the index of its layer
+    fn add_layer(
+        &mut self,
+        client_type: ClientType,
+        opacity: f64,
+        blend: &str,
+    ) -> zbus::fdo::Result<u32> {
+        let blend = parse_blend(blend)?;
+        let layer = Layer {
+            client_type,
+            effect: RunningEffect::new(start_effect(client_type)?),
+            opacity: opacity.clamp(0.0, 1.0) as f32,
+            blend,
+        };
+
+        let index = self.scene.lock().unwrap().layers_mut().push(layer);
+        Ok(index as u32)
+    }
+
+    fn remove_layer(&mut self, index: u32) -> zbus::fdo::Result<()> {
+        self.scene
+            .lock()
+            .unwrap()
+            .layers_mut()
+            .remove(index as usize)
+            .map(|_| ())
+            .ok_or_else(|| no_layer(index))
+    }
+
+    fn set_layer_opacity(&mut self, index: u32, opacity: f64) -> zbus::fdo::Result<()> {
+        let mut scene = self.scene.lock().unwrap();
+        let layer = scene
+            .layers_mut()
+            .get_mut(index as usize)
+            .ok_or_else(|| no_layer(index))?;
+
+        layer.opacity = opacity.clamp(0.0, 1.0) as f32;
+        Ok(())
+    }
+
+    fn set_layer_blend(&mut self, index: u32, blend: &str) -> zbus::fdo::Result<()> {
+        let blend = parse_blend(blend)?;
+        let mut scene = self.scene.lock().unwrap();
+        let layer = scene
+            .layers_mut()
+            .get_mut(index as usize)
+            .ok_or_else(|| no_layer(index))?;
+
+        layer.blend = blend;
+        Ok(())
+    }
+
+    fn clear_layers(&mut self) {
+        self.scene.lock().unwrap().layers_mut().clear();
+    }
+
+    /// The effect, opacity and blend mode of every layer, lowest first
+    fn layers(&self) -> Vec<(ClientType, f64, String)> {
+        self.scene
+            .lock()
+            .unwrap()
+            .layers()
+            .layers()
+            .iter()
+            .map(|layer| {
+                (
+                    layer.client_type,
+                    layer.opacity as f64,
+                    layer.blend.to_string(),
+                )
+            })
+            .collect()
+    }
+
+    /// The name, description, type, accepted range, default and current value of every
+    /// parameter of the current effect
+    #[allow(clippy::type_complexity)]
+    fn get_parameters(&self) -> Vec<(String, String, String, String, String, String)> {
+        let scene = self.scene.lock().unwrap();
+        let Some(parameters) = scene.current().parameters() else {
+            return Vec::new();
+        };
+
+        parameters
+            .iter()
+            .map(|(parameter, value)| {
+                (
+                    parameter.name.to_string(),
+                    parameter.description.to_string(),
+                    parameter.kind.name().to_string(),
+                    parameter.kind.range(),
+                    parameter.default.to_string(),
+                    value.to_string(),
+                )
+            })
+            .collect()
+    }
+
+    fn set_parameter(&mut self, name: &str, value: &str) -> zbus::fdo::Result<()> {
+        self.scene
+            .lock()
+            .unwrap()
+            .current_mut()
+            .set_parameter(name, value)
+            .map_err(|err| zbus::fdo::Error::InvalidArgs(format!("{:#}", err)))
+    }
+
+    /// Plays a playlist from the config, a manually set effect stops it
+    fn play_playlist(&mut self, name: &str) -> zbus::fdo::Result<()> {
+        if !config::get().playlists.contains_key(name) {
+            return Err(zbus::fdo::Error::InvalidArgs(format!(
+                "Unknown playlist {}",
+                name
+            )));
+        }
+
+        let (playlist, effect) = start_playlist(name).map_err(playlist_error)?;
+        self.scene.lock().unwrap().play_playlist(playlist, effect);
+        Ok(())
+    }
+
+    fn next(&mut self) -> zbus::fdo::Result<()> {
+        self.skip(Playlist::next)
+    }
+
+    fn previous(&mut self) -> zbus::fdo::Result<()> {
+        self.skip(Playlist::previous)
+    }
+
+    /// Flashes `length` leds from `start` over the current effect `count` times, 0 covers
+    /// the rest of the strip
+    fn flash(
+        &mut self,
+        color: &str,
+        start: u32,
+        length: u32,
+        count: u32,
+        period_ms: u32,
+        priority: u8,
+    ) -> zbus::fdo::Result<()> {
+        if count == 0 {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "A flash needs a count above 0".to_string(),
+            ));
+        }
+
+        self.add_overlay(
+            color,
+            start,
+            length,
+            period_ms,
+            OverlayKind::Flash { count },
+            priority,
+        )
+    }
+
+    /// Pulses `length` leds from `start` over the current effect for `duration_ms`, 0 covers
+    /// the rest of the strip
+    fn pulse(
+        &mut self,
+        color: &str,
+        start: u32,
+        length: u32,
+        duration_ms: u32,
+        period_ms: u32,
+        priority: u8,
+    ) -> zbus::fdo::Result<()> {
+        if duration_ms == 0 {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "A pulse needs a duration above 0".to_string(),
+            ));
+        }
+
+        self.add_overlay(
+            color,
+            start,
+            length,
+            period_ms,
+            OverlayKind::Pulse {
+                duration: Duration::from_millis(duration_ms as u64),
+            },
+            priority,
+        )
+    }
+
+    fn clear_overlays(&mut self) {
+        self.scene.lock().unwrap().overlays_mut().clear();
+    }
+
+    /// Shows a frame of raw RGB bytes, switching to the Stream effect until frames stop arriving
+    fn push_frame(&mut self, frame: Vec<u8>) -> zbus::fdo::Result<()> {
+        if !frame.len().is_multiple_of(3) {
+            return Err(zbus::fdo::Error::InvalidArgs(format!(
+                "Frames are RGB, the length must be a multiple of 3, got {}",
+                frame.len()
+            )));
+        }
+
+        self.scene.lock().unwrap().push_frame(
+            frame
+                .chunks_exact(3)
+                .map(|rgb| Color::new(rgb[0], rgb[1], rgb[2]))
+                .collect(),
+        );
+        Ok(())
+    }
+
+    /// Stops updating the effects, leaving the current frame on the strip
+    fn pause(&mut self) {
+        self.scene.lock().unwrap().set_paused(true);
+    }
+
+    /// Leaves the current frame on the strip while the effects keep running underneath
+    fn freeze(&mut self) {
+        self.scene.lock().unwrap().set_frozen(true);
+    }
+
+    /// Turns every led off and stops updating the effects
+    fn blackout(&mut self) {
+        self.scene.lock().unwrap().set_blackout(true);
+    }
+
+    /// Undoes a pause, freeze or blackout, paused effects carry on from where they were
+    fn resume(&mut self) {
+        self.scene.lock().unwrap().resume();
+    }
+
+    fn set_brightness(&mut self, brightness: f64) {
+        *self.brightness.lock().unwrap() = brightness.clamp(0.0, 1.0) as f32;
+    }
+
+    /// Consecutive errors from the current effect, errors since the daemon started, the last
+    /// error, empty if there has not been one, and whether the effect is stuck in an update
+    async fn effect_status(&self) -> zbus::fdo::Result<(u32, u64, String, bool)> {
+        self.read_scene(|scene| {
+            let recovery = scene.recovery();
+            (
+                recovery.consecutive(),
+                recovery.total(),
+                recovery.last_error().unwrap_or_default().to_string(),
+                scene.current().is_hung(),
+            )
+        })
+        .await
+    }
+
+    /// Frames rendered, frames skipped because the render loop fell behind and the current fps
+    fn render_stats(&self) -> (u64, u64, f64) {
+        let stats = self.stats.lock().unwrap();
+        (stats.frames, stats.skipped, stats.fps as f64)
     }
 
     fn end_daemon(&self) {
         process::exit(0)
     }
+
+    #[dbus_interface(property)]
+    async fn current_effect(&self) -> zbus::fdo::Result<String> {
+        self.read_scene(|scene| scene.client_type().to_string())
+            .await
+    }
+
+    #[dbus_interface(property)]
+    fn available_effects(&self) -> Vec<String> {
+        enum_iterator::all::<ClientType>()
+            .map(|client_type| client_type.to_string())
+            .collect()
+    }
+
+    #[dbus_interface(property)]
+    fn brightness(&self) -> f64 {
+        *self.brightness.lock().unwrap() as f64
+    }
+
+    /// Changes every frame so is not signalled, poll it instead
+    #[dbus_interface(property)]
+    fn fps(&self) -> f64 {
+        self.stats.lock().unwrap().fps as f64
+    }
+
+    #[dbus_interface(property)]
+    async fn paused(&self) -> zbus::fdo::Result<bool> {
+        self.read_scene(Scene::is_paused).await
+    }
+
+    #[dbus_interface(property)]
+    async fn frozen(&self) -> zbus::fdo::Result<bool> {
+        self.read_scene(Scene::is_frozen).await
+    }
+
+    #[dbus_interface(property)]
+    async fn blacked_out(&self) -> zbus::fdo::Result<bool> {
+        self.read_scene(Scene::is_blackout).await
+    }
+
+    /// The last error from an effect, empty if there has not been one
+    #[dbus_interface(property)]
+    async fn last_error(&self) -> zbus::fdo::Result<String> {
+        self.read_scene(|scene| {
+            scene
+                .recovery()
+                .last_error()
+                .unwrap_or_default()
+                .to_string()
+        })
+        .await
+    }
+
+    #[dbus_interface(signal)]
+    async fn effect_changed(ctxt: &SignalContext<'_>, effect: &str) -> zbus::Result<()>;
+}
+
+fn parse_blend(blend: &str) -> zbus::fdo::Result<BlendMode> {
+    blend
+        .parse()
+        .map_err(|_| zbus::fdo::Error::InvalidArgs(format!("Unknown blend mode {}", blend)))
+}
+
+/// Creates an effect, a failure is returned to the caller rather than taking the daemon down
+fn start_effect(client_type: ClientType) -> zbus::fdo::Result<Box<dyn Effect + Send>> {
+    client_type.into_effect().map_err(|err| {
+        println!("Error starting {}: {:#}", client_type, err);
+        zbus::fdo::Error::Failed(format!("Failed to start {}: {:#}", client_type, err))
+    })
+}
+
+fn playlist_error(err: anyhow::Error) -> zbus::fdo::Error {
+    zbus::fdo::Error::Failed(format!("{:#}", err))
+}
+
+fn no_layer(index: u32) -> zbus::fdo::Error {
+    zbus::fdo::Error::InvalidArgs(format!("No layer {}", index))
+}
+
+impl BusInterface {
+    /// Reads the scene on a blocking thread, the render thread keeps it locked while it updates
+    /// the effects and that must not hold up the executor
+    async fn read_scene<T: Send + 'static>(
+        &self,
+        read: impl FnOnce(&Scene) -> T + Send + 'static,
+    ) -> zbus::fdo::Result<T> {
+        let scene = Arc::clone(&self.scene);
+        self.runtime
+            .spawn_blocking(move || read(&scene.lock().unwrap()))
+            .await
+            .map_err(|err| zbus::fdo::Error::Failed(format!("Error reading the scene: {}", err)))
+    }
+
+    fn add_overlay(
+        &self,
+        color: &str,
+        start: u32,
+        length: u32,
+        period_ms: u32,
+        kind: OverlayKind,
+        priority: u8,
+    ) -> zbus::fdo::Result<()> {
+        let color = parse_color(color).ok_or_else(|| {
+            zbus::fdo::Error::InvalidArgs(format!("Expected a colour like #ff0000, got {}", color))
+        })?;
+
+        if period_ms == 0 {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "The period must be above 0".to_string(),
+            ));
+        }
+
+        let overlay = Overlay {
+            color,
+            start: start as usize,
+            length: length as usize,
+            period: Duration::from_millis(period_ms as u64),
+            kind,
+            priority,
+        };
+
+        self.scene
+            .lock()
+            .unwrap()
+            .overlays_mut()
+            .push(overlay, Instant::now());
+        Ok(())
+    }
+
+    /// Switches to a new effect, the current one keeps running if it cannot be created
+    fn change_effect(
+        &self,
+        client_type: ClientType,
+        transition: Transition,
+    ) -> zbus::fdo::Result<()> {
+        let effect = RunningEffect::new(start_effect(client_type)?);
+
+        let mut scene = self.scene.lock().unwrap();
+        scene.stop_playlist();
+        scene.set_effect(client_type, effect, transition);
+        Ok(())
+    }
+
+    /// Moves through the playlist and switches to the entry it lands on, the entry's effect is
+    /// created without the scene locked
+    fn skip(&self, step: fn(&mut Playlist, Instant)) -> zbus::fdo::Result<()> {
+        let (name, entry) = {
+            let mut scene = self.scene.lock().unwrap();
+            let playlist = scene
+                .playlist_mut()
+                .ok_or_else(|| playlist_error(anyhow!("no playlist is playing")))?;
+            step(playlist, Instant::now());
+            (playlist.name().to_string(), playlist.current().clone())
+        };
+
+        let effect = playlist::start_entry(&name, &entry).map_err(playlist_error)?;
+        self.scene.lock().unwrap().set_effect(
+            entry.effect,
+            effect,
+            config::get().transition.transition(),
+        );
+        Ok(())
+    }
 }
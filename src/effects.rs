@@ -2,6 +2,8 @@ pub mod coding;
 pub mod music_visualiser;
 pub mod rainbow;
 pub mod random;
+pub mod replay;
+pub mod stream;
 pub mod test;
 pub mod typing_ripple;
 
@@ -9,5 +11,434 @@ pub use coding::CodingEffect;
 pub use music_visualiser::MusicVisualiserEffect;
 pub use rainbow::RainbowEffect;
 pub use random::RandomEffect;
+pub use replay::ReplayEffect;
+pub use stream::StreamEffect;
 pub use test::TestEffect;
 pub use typing_ripple::TypingRippleEffect;
+
+use std::{
+    sync::mpsc::{self, Receiver, Sender, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::{
+    config, led_count, ClientType, Color, Effect, EffectConfig, FrameContext, LedData, Parameters,
+};
+
+/// Work sent to the thread an effect runs on
+enum Request {
+    Update(FrameContext),
+    /// Only sent once the value has been checked against the effect's parameters
+    SetParameter {
+        name: String,
+        value: String,
+    },
+}
+
+/// An effect along with the timing state used to build its [`FrameContext`]. The effect runs on
+/// its own thread and is never waited on, each update takes the frame that was asked for last
+/// time, so one that blocks inside `update` cannot hold up the render loop. It is reported as
+/// hung once an update takes longer than `recovery.timeout_ms`
+pub struct RunningEffect {
+    requests: Sender<Request>,
+    frames: Receiver<Result<Option<LedData>>>,
+    config: EffectConfig,
+    timeout: Duration,
+    /// When the update whose frame is waited on was asked for
+    requested_at: Instant,
+    /// The update asked for has taken longer than `timeout`
+    hung: bool,
+    time: Duration,
+    frame: u64,
+    last_update: Option<Instant>,
+    last_frame: Option<LedData>,
+    /// A copy of the effect's parameters, kept here so they never have to be asked for
+    parameters: Option<Parameters>,
+    /// Parameters set since the effect started, in the order they were set
+    set_parameters: Vec<(String, String)>,
+}
+
+impl RunningEffect {
+    pub fn new(effect: Box<dyn Effect + Send>) -> Self {
+        let config = effect.get_config();
+        let parameters = effect.parameters().cloned();
+        let (requests, requests_rx) = mpsc::channel();
+        let (frames_tx, frames) = mpsc::channel();
+
+        // A hung thread is left behind when the effect is replaced, it exits once it wakes up
+        // and finds the channels closed
+        thread::Builder::new()
+            .name("effect".to_string())
+            .spawn(move || run(effect, requests_rx, frames_tx))
+            .expect("failed to spawn an effect thread");
+
+        let mut running = Self {
+            requests,
+            frames,
+            config,
+            timeout: Duration::from_millis(config::get().recovery.timeout_ms),
+            requested_at: Instant::now(),
+            hung: false,
+            time: Duration::ZERO,
+            frame: 0,
+            last_update: None,
+            last_frame: None,
+            parameters,
+            set_parameters: Vec::new(),
+        };
+        // The first frame is worked on straight away so it is ready for the first update
+        running.request_update();
+        running
+    }
+
+    /// Takes the frame the effect was last asked for, if it is ready, and asks for the next one
+    pub fn update(&mut self) -> Result<Option<LedData>> {
+        let data = match self.frames.try_recv() {
+            Ok(data) => data,
+            Err(TryRecvError::Empty) if self.requested_at.elapsed() > self.timeout => {
+                self.hung = true;
+                bail!("update took longer than {:?}", self.timeout)
+            }
+            Err(TryRecvError::Empty) => return Ok(None),
+            Err(TryRecvError::Disconnected) => bail!("the effect stopped"),
+        };
+        self.hung = false;
+        self.request_update();
+
+        let data = data?;
+        if let Some(data) = &data {
+            self.last_frame = Some(data.clone());
+        }
+        Ok(data)
+    }
+
+    fn request_update(&mut self) {
+        let ctx = self.next_context();
+        // The thread has only stopped if the effect panicked, the next update reports it
+        let _ = self.requests.send(Request::Update(ctx));
+        self.requested_at = Instant::now();
+    }
+
+    fn next_context(&mut self) -> FrameContext {
+        let now = Instant::now();
+        let delta = self
+            .last_update
+            .map(|last_update| now.duration_since(last_update))
+            .unwrap_or_default();
+        self.last_update = Some(now);
+        self.time += delta;
+
+        let ctx = FrameContext {
+            delta,
+            time: self.time,
+            frame: self.frame,
+            led_count: led_count(),
+        };
+        self.frame += 1;
+        ctx
+    }
+
+    /// Carries on from the same time on the next update, rather than counting the time the effect
+    /// was not updated for
+    pub fn resume(&mut self) {
+        self.last_update = None;
+    }
+
+    /// Whether the effect is stuck in an update that overran
+    pub fn is_hung(&self) -> bool {
+        self.hung
+    }
+
+    /// The most recent frame the effect returned
+    pub fn last_frame(&self) -> Option<&LedData> {
+        self.last_frame.as_ref()
+    }
+
+    /// Updates the effect and returns its newest frame, whether or not it changed, a failing
+    /// effect keeps showing its last frame
+    pub fn render(&mut self) -> LedData {
+        let _ = self.update();
+        self.last_frame().cloned().unwrap_or_else(black)
+    }
+
+    pub fn get_config(&self) -> EffectConfig {
+        self.config
+    }
+
+    /// The effect's parameters and their current values
+    pub fn parameters(&self) -> Option<&Parameters> {
+        self.parameters.as_ref()
+    }
+
+    /// Checks the value and passes it on to the effect without waiting for it
+    pub fn set_parameter(&mut self, name: &str, value: &str) -> Result<()> {
+        let parameters = self
+            .parameters
+            .as_mut()
+            .ok_or_else(|| anyhow!("the effect has no parameters"))?;
+        parameters.set(name, value)?;
+
+        let request = Request::SetParameter {
+            name: name.to_string(),
+            value: value.to_string(),
+        };
+        self.requests
+            .send(request)
+            .map_err(|_| anyhow!("the effect stopped"))?;
+
+        self.set_parameters.retain(|(set, _)| set != name);
+        self.set_parameters
+            .push((name.to_string(), value.to_string()));
+        Ok(())
+    }
+
+    /// The parameters set since the effect started, see [`RunningEffect::restore_parameters`]
+    pub fn changed_parameters(&self) -> &[(String, String)] {
+        &self.set_parameters
+    }
+
+    /// Sets parameters taken from another instance, so a restarted effect keeps them
+    pub fn restore_parameters(&mut self, parameters: &[(String, String)]) {
+        for (name, value) in parameters {
+            if let Err(err) = self.set_parameter(name, value) {
+                println!("Error restoring parameter {}: {:#}", name, err);
+            }
+        }
+    }
+}
+
+/// An effect being created on its own thread, as creating one can block, e.g. while the Coding
+/// effect connects to neovim, and the render loop has to keep going meanwhile
+pub struct Starting {
+    client_type: ClientType,
+    effect: Receiver<Result<RunningEffect>>,
+}
+
+impl Starting {
+    pub fn spawn(
+        client_type: ClientType,
+        start: impl FnOnce() -> Result<RunningEffect> + Send + 'static,
+    ) -> Self {
+        let (effect_tx, effect) = mpsc::channel();
+
+        // Nothing is waiting on the effect once the start is cancelled, so a failed send is fine
+        thread::Builder::new()
+            .name("effect start".to_string())
+            .spawn(move || {
+                let _ = effect_tx.send(start());
+            })
+            .expect("failed to spawn an effect start thread");
+
+        Self {
+            client_type,
+            effect,
+        }
+    }
+
+    pub fn client_type(&self) -> ClientType {
+        self.client_type
+    }
+
+    /// The created effect, or None while it is still being created
+    pub fn poll(&self) -> Option<Result<RunningEffect>> {
+        match self.effect.try_recv() {
+            Ok(effect) => Some(effect),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(anyhow!("creating the effect panicked"))),
+        }
+    }
+}
+
+/// The effect thread, handles requests until the [`RunningEffect`] is dropped
+fn run(
+    mut effect: Box<dyn Effect + Send>,
+    requests: Receiver<Request>,
+    frames: Sender<Result<Option<LedData>>>,
+) {
+    for request in requests {
+        match request {
+            // Fails once the effect has been replaced, which is fine to ignore
+            Request::Update(ctx) => {
+                let _ = frames.send(effect.update(&ctx));
+            }
+            Request::SetParameter { name, value } => {
+                if let Some(parameters) = effect.parameters_mut() {
+                    let _ = parameters.set(&name, &value);
+                }
+            }
+        }
+    }
+}
+
+fn black() -> LedData {
+    vec![Color::BLACK; led_count()]
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::parameters;
+
+    /// An effect for tests that shows a single colour, it can also report, block or fail
+    pub struct FakeEffect {
+        pub color: Color,
+        /// Only the first update returns a frame, like an effect that never changes
+        pub once: bool,
+        /// Updates fail from this frame on
+        pub fail_from: Option<u64>,
+        /// Receives the context of every update
+        pub contexts: Option<Sender<FrameContext>>,
+        /// Every update waits for a message before it carries on
+        pub block: Option<Receiver<()>>,
+        pub parameters: Option<Parameters>,
+    }
+
+    impl Default for FakeEffect {
+        fn default() -> Self {
+            Self {
+                color: Color::RED,
+                once: false,
+                fail_from: None,
+                contexts: None,
+                block: None,
+                parameters: None,
+            }
+        }
+    }
+
+    impl Effect for FakeEffect {
+        fn update(&mut self, ctx: &FrameContext) -> Result<Option<LedData>> {
+            if let Some(contexts) = &self.contexts {
+                let _ = contexts.send(*ctx);
+            }
+
+            if let Some(block) = &self.block {
+                block.recv()?;
+            }
+
+            if self.fail_from.is_some_and(|frame| ctx.frame >= frame) {
+                bail!("lost the device");
+            }
+
+            if self.once && ctx.frame > 0 {
+                return Ok(None);
+            }
+
+            Ok(Some(vec![self.color; ctx.led_count]))
+        }
+
+        fn parameters(&self) -> Option<&Parameters> {
+            self.parameters.as_ref()
+        }
+
+        fn parameters_mut(&mut self) -> Option<&mut Parameters> {
+            self.parameters.as_mut()
+        }
+
+        fn new() -> Result<Self> {
+            Ok(Self::default())
+        }
+    }
+
+    fn reporting(contexts: Sender<FrameContext>) -> RunningEffect {
+        RunningEffect::new(Box::new(FakeEffect {
+            contexts: Some(contexts),
+            ..FakeEffect::default()
+        }))
+    }
+
+    #[test]
+    fn builds_frame_context() {
+        let (tx, rx) = mpsc::channel();
+        let mut effect = reporting(tx);
+
+        // The first frame is asked for straight away, the second once it has been taken
+        thread::sleep(Duration::from_millis(5));
+        effect.update().unwrap();
+
+        let first = rx.recv().unwrap();
+        let second = rx.recv().unwrap();
+
+        assert_eq!(
+            (first.frame, first.time, first.delta),
+            (0, Duration::ZERO, Duration::ZERO)
+        );
+        assert_eq!(second.frame, 1);
+        assert!(second.delta >= Duration::from_millis(5));
+        assert_eq!(second.time, second.delta);
+        assert_eq!(second.led_count, led_count());
+    }
+
+    #[test]
+    fn resumes_without_a_time_jump() {
+        let (tx, rx) = mpsc::channel();
+        let mut effect = reporting(tx);
+
+        thread::sleep(Duration::from_millis(5));
+        effect.update().unwrap();
+        thread::sleep(Duration::from_millis(20));
+        effect.resume();
+        thread::sleep(Duration::from_millis(5));
+        effect.update().unwrap();
+
+        let contexts: Vec<_> = rx.iter().take(3).collect();
+        assert_eq!(contexts[2].frame, 2);
+        assert_eq!(contexts[2].time, contexts[1].time);
+    }
+
+    #[test]
+    fn restores_parameters() {
+        let with_parameters = || {
+            RunningEffect::new(Box::new(FakeEffect {
+                parameters: Some(parameters::tests::parameters()),
+                ..FakeEffect::default()
+            }))
+        };
+
+        let mut effect = with_parameters();
+        effect.set_parameter("speed", "2").unwrap();
+        effect.set_parameter("size", "20").unwrap();
+        effect.set_parameter("speed", "3").unwrap();
+        assert!(effect.set_parameter("speed", "-1").is_err());
+
+        let mut restarted = with_parameters();
+        restarted.restore_parameters(effect.changed_parameters());
+
+        let parameters = restarted.parameters().unwrap();
+        assert_eq!(parameters.float("speed"), 3.0);
+        assert_eq!(parameters.integer("size"), 20);
+    }
+
+    #[test]
+    fn reports_hung_effects() {
+        let (tx, rx) = mpsc::channel();
+        let mut effect = RunningEffect::new(Box::new(FakeEffect {
+            block: Some(rx),
+            parameters: Some(parameters::tests::parameters()),
+            ..FakeEffect::default()
+        }));
+        effect.timeout = Duration::from_millis(10);
+
+        // The effect is not waited on
+        assert!(effect.update().unwrap().is_none());
+        assert!(!effect.is_hung());
+
+        thread::sleep(Duration::from_millis(20));
+        assert!(effect.update().is_err());
+        assert!(effect.is_hung());
+        // Parameters are set without waiting on the effect
+        effect.set_parameter("speed", "2").unwrap();
+        assert_eq!(effect.parameters().unwrap().float("speed"), 2.0);
+        assert!(effect.set_parameter("speed", "-1").is_err());
+
+        // The overrunning update is picked up once it finishes
+        tx.send(()).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert!(effect.update().unwrap().is_some());
+        assert!(!effect.is_hung());
+        assert_eq!(effect.render()[0], Color::RED);
+    }
+}
@@ -1,12 +1,15 @@
-use std::{fs, iter, path::Path};
+use std::{fs, path::Path};
 
+use anyhow::Context;
 use neovim_lib::{Neovim, NeovimApi, Session};
 
-use crate::{helpers::vec_to_led_data, Color, Effect, LED_SIZE};
+use crate::parameters::{Parameter, ParameterKind, ParameterValue, Parameters};
+use crate::{Color, Effect, FrameContext};
 
 pub struct CodingEffect {
     nvim: Neovim,
-    last_mode: Option<Mode>,
+    last_color: Option<Color>,
+    parameters: Parameters,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -17,6 +20,27 @@ enum Mode {
     Command,
 }
 
+impl Mode {
+    /// The parameter holding the mode's colour
+    fn parameter(&self) -> &'static str {
+        match self {
+            Mode::Normal => "normal",
+            Mode::Insert => "insert",
+            Mode::Visual => "visual",
+            Mode::Command => "command",
+        }
+    }
+}
+
+fn color_parameter(name: &'static str, description: &'static str, default: Color) -> Parameter {
+    Parameter {
+        name,
+        description,
+        kind: ParameterKind::Color,
+        default: ParameterValue::Color(default),
+    }
+}
+
 fn find_nvim_socket() -> Result<Option<String>, std::io::Error> {
     let dir = Path::new("/run/user/1000");
 
@@ -32,22 +56,37 @@ fn find_nvim_socket() -> Result<Option<String>, std::io::Error> {
 }
 
 impl Effect for CodingEffect {
-    fn new() -> Self
+    fn new() -> anyhow::Result<Self>
     where
         Self: Sized,
     {
-        let path = find_nvim_socket().unwrap().expect("no nvim socket found");
-        let session = Session::new_unix_socket(path);
-        let mut nvim = Neovim::new(session.unwrap());
+        let path = find_nvim_socket()?.context("no nvim socket found")?;
+        let session = Session::new_unix_socket(&path)
+            .with_context(|| format!("failed to connect to {}", path))?;
+        let mut nvim = Neovim::new(session);
         nvim.session.start_event_loop();
 
-        CodingEffect {
+        Ok(CodingEffect {
             nvim,
-            last_mode: None,
-        }
+            last_color: None,
+            parameters: Parameters::new(vec![
+                color_parameter("normal", "Colour in normal mode", Color::BLUE),
+                color_parameter("insert", "Colour in insert mode", Color::GREEN),
+                color_parameter("visual", "Colour in visual mode", Color::PURPLE),
+                color_parameter("command", "Colour in command mode", Color::ORANGE),
+            ]),
+        })
+    }
+
+    fn parameters(&self) -> Option<&Parameters> {
+        Some(&self.parameters)
     }
 
-    fn update(&mut self) -> anyhow::Result<Option<crate::LedData>> {
+    fn parameters_mut(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.parameters)
+    }
+
+    fn update(&mut self, ctx: &FrameContext) -> anyhow::Result<Option<crate::LedData>> {
         let mode = self.nvim.get_mode()?;
         let mode = mode[0].1.as_str().unwrap();
 
@@ -60,23 +99,15 @@ impl Effect for CodingEffect {
             _ => Mode::Command,
         };
 
-        let last_mode = &self.last_mode;
+        let color = self.parameters.color(mode.parameter());
 
-        if last_mode.as_ref().is_some_and(|l| l == &mode) {
+        // Compared by colour so changing a mode's colour shows straight away
+        if self.last_color == Some(color) {
             return Ok(None);
         }
 
-        self.last_mode = Some(mode.clone());
-
-        let color = match mode {
-            Mode::Normal => Color::BLUE,
-            Mode::Insert => Color::GREEN,
-            Mode::Visual => Color::PURPLE,
-            Mode::Command => Color::ORANGE,
-        };
+        self.last_color = Some(color);
 
-        Ok(Some(vec_to_led_data(
-            iter::repeat(color).take(LED_SIZE).collect(),
-        )))
+        Ok(Some(vec![color; ctx.led_count]))
     }
 }
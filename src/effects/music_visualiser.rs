@@ -1,5 +1,3 @@
-use std::time::Duration;
-
 use audioviz::{
     io::{Device, Input, InputController},
     spectrum::{
@@ -8,10 +6,7 @@ use audioviz::{
     },
 };
 
-use crate::{
-    helpers::{get_random_color, vec_to_led_data},
-    Color, Effect, LED_SIZE,
-};
+use crate::{helpers::get_random_color, led_count, Color, Effect, FrameContext};
 
 pub struct MusicVisualiserEffect {
     _audio_input: Input,
@@ -23,7 +18,7 @@ pub struct MusicVisualiserEffect {
 unsafe impl Send for MusicVisualiserEffect {}
 
 impl Effect for MusicVisualiserEffect {
-    fn update(&mut self) -> anyhow::Result<Option<crate::LedData>> {
+    fn update(&mut self, ctx: &FrameContext) -> anyhow::Result<Option<crate::LedData>> {
         if let Some(data) = self.input_controller.pull_data() {
             self.stream.push_data(data);
             self.stream.update();
@@ -32,15 +27,16 @@ impl Effect for MusicVisualiserEffect {
         let frequencies = self.stream.get_frequencies();
         let frequencies = frequencies.first().unwrap().iter();
 
-        let frequencies = frequencies.map(|f| (f.volume.clamp(0.0, 1.0)));
+        let frequencies = frequencies.map(|f| f.volume.clamp(0.0, 1.0));
 
         if frequencies.clone().all(|f| f == 0.0) {
             self.color = get_random_color();
         };
 
-        let frequencies: Vec<f32> = frequencies.collect();
+        let mut frequencies: Vec<f32> = frequencies.collect();
+        frequencies.resize(ctx.led_count, 0.0);
 
-        Ok(Some(vec_to_led_data(
+        Ok(Some(
             frequencies
                 .iter()
                 .map(|f| -> Color {
@@ -49,26 +45,26 @@ impl Effect for MusicVisualiserEffect {
                     color
                 })
                 .collect(),
-        )))
+        ))
     }
 
     fn get_config(&self) -> crate::EffectConfig {
-        crate::EffectConfig {
-            delay: Duration::ZERO,
-        }
+        crate::EffectConfig { fps: 120.0 }
     }
 
-    fn new() -> Self
+    fn new() -> anyhow::Result<Self>
     where
         Self: Sized,
     {
         let mut audio_input = Input::new();
-        let (_, _, input_controller) = audio_input.init(&Device::DefaultInput, None).unwrap();
+        let (_, _, input_controller) = audio_input
+            .init(&Device::DefaultInput, None)
+            .map_err(|err| anyhow::anyhow!("failed to open the audio input: {:?}", err))?;
 
         let config = StreamConfig {
             channel_count: 1,
             processor: ProcessorConfig {
-                resolution: Some(LED_SIZE),
+                resolution: Some(led_count()),
                 volume_normalisation: VolumeNormalisation::Mixture,
                 interpolation: Interpolation::Cubic,
                 ..ProcessorConfig::default()
@@ -77,11 +73,11 @@ impl Effect for MusicVisualiserEffect {
         };
         let stream = Stream::new(config);
 
-        MusicVisualiserEffect {
+        Ok(MusicVisualiserEffect {
             _audio_input: audio_input,
             input_controller,
             stream,
             color: get_random_color(),
-        }
+        })
     }
 }
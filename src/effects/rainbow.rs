@@ -1,38 +1,55 @@
-use crate::{helpers::vec_to_led_data, Color, Effect, LedData, LED_SIZE};
-use std::{collections::VecDeque, f64::consts::PI};
+use crate::parameters::{Parameter, ParameterKind, ParameterValue, Parameters};
+use crate::{Color, Effect, FrameContext, LedData};
+use std::f64::consts::PI;
+
+const SPEED: &str = "speed";
 
 #[derive(Clone)]
 pub struct RainbowEffect {
-    iterator: usize,
+    parameters: Parameters,
 }
 
 impl Effect for RainbowEffect {
-    fn new() -> Self {
-        Self { iterator: 0 }
+    fn new() -> anyhow::Result<Self> {
+        Ok(Self {
+            parameters: Parameters::new(vec![Parameter {
+                name: SPEED,
+                description: "How far the rainbow moves along the strip, in leds per second",
+                kind: ParameterKind::Float {
+                    min: -1000.0,
+                    max: 1000.0,
+                },
+                default: ParameterValue::Float(100.0),
+            }]),
+        })
+    }
+
+    fn parameters(&self) -> Option<&Parameters> {
+        Some(&self.parameters)
+    }
+
+    fn parameters_mut(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.parameters)
     }
 
-    fn update(&mut self) -> anyhow::Result<Option<LedData>> {
-        let mut data: VecDeque<Color> = VecDeque::with_capacity(LED_SIZE);
-        for i in 0..LED_SIZE {
-            let phase_r = i as f64 * 2.0 * PI / LED_SIZE as f64;
-            let phase_g = (i as f64 * 2.0 * PI / LED_SIZE as f64) + (2.0 * PI / 3.0);
-            let phase_b = (i as f64 * 2.0 * PI / LED_SIZE as f64) + (4.0 * PI / 3.0);
+    fn update(&mut self, ctx: &FrameContext) -> anyhow::Result<Option<LedData>> {
+        let led_count = ctx.led_count as f64;
+        let offset = (ctx.time.as_secs_f64() * self.parameters.float(SPEED)) % led_count;
+
+        let mut data: Vec<Color> = Vec::with_capacity(ctx.led_count);
+        for i in 0..ctx.led_count {
+            let phase = (i as f64 - offset) * 2.0 * PI / led_count;
+            let phase_r = phase;
+            let phase_g = phase + (2.0 * PI / 3.0);
+            let phase_b = phase + (4.0 * PI / 3.0);
 
             let r = ((phase_r).sin() * 127.0 + 128.0) as u8;
             let g = ((phase_g).sin() * 127.0 + 128.0) as u8;
             let b = ((phase_b).sin() * 127.0 + 128.0) as u8;
 
-            data.push_back(Color::new(r, g, b));
-        }
-
-        data.rotate_right(self.iterator);
-        let data = Vec::from(data);
-
-        self.iterator += 1;
-        if self.iterator >= data.len() {
-            self.iterator = 0;
+            data.push(Color::new(r, g, b));
         }
 
-        Ok(Some(vec_to_led_data(data)))
+        Ok(Some(data))
     }
 }
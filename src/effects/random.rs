@@ -1,30 +1,48 @@
 use rand::thread_rng;
 
-use crate::helpers::{get_random_color_with_rng, vec_to_led_data};
-use crate::{Color, Effect, LedData, LED_SIZE};
-use std::time::Duration;
+use crate::helpers::get_random_color_with_rng;
+use crate::parameters::{Parameter, ParameterKind, ParameterValue, Parameters};
+use crate::{Color, Effect, FrameContext, LedData};
+
+const BLOCK_SIZE: &str = "block_size";
 
 #[derive(Clone)]
-pub struct RandomEffect;
+pub struct RandomEffect {
+    parameters: Parameters,
+}
 impl Effect for RandomEffect {
-    fn new() -> Self {
-        Self {}
+    fn new() -> anyhow::Result<Self> {
+        Ok(Self {
+            parameters: Parameters::new(vec![Parameter {
+                name: BLOCK_SIZE,
+                description: "How many leds share each random colour",
+                kind: ParameterKind::Integer { min: 1, max: 1000 },
+                default: ParameterValue::Integer(10),
+            }]),
+        })
     }
-    fn update(&mut self) -> anyhow::Result<Option<LedData>> {
-        const BLOCK_SIZE: usize = 10;
-        let mut data: Vec<Color> = Vec::with_capacity(LED_SIZE);
+    fn update(&mut self, ctx: &FrameContext) -> anyhow::Result<Option<LedData>> {
+        let block_size = self.parameters.integer(BLOCK_SIZE) as usize;
+        let mut data: Vec<Color> = Vec::with_capacity(ctx.led_count.next_multiple_of(block_size));
         let mut rng = thread_rng();
-        for _ in 0..(LED_SIZE / BLOCK_SIZE) {
-            for _ in 0..BLOCK_SIZE {
+        for _ in 0..ctx.led_count.div_ceil(block_size) {
+            for _ in 0..block_size {
                 data.push(get_random_color_with_rng(&mut rng));
             }
         }
-        Ok(Some(vec_to_led_data(data)))
+        data.truncate(ctx.led_count);
+        Ok(Some(data))
     }
 
     fn get_config(&self) -> crate::EffectConfig {
-        crate::EffectConfig {
-            delay: Duration::from_secs(1),
-        }
+        crate::EffectConfig { fps: 1.0 }
+    }
+
+    fn parameters(&self) -> Option<&Parameters> {
+        Some(&self.parameters)
+    }
+
+    fn parameters_mut(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.parameters)
     }
 }
@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use anyhow::Context;
+
+use crate::{
+    config,
+    recording::{self, Frame},
+    Color, Effect, FrameContext, LedData,
+};
+
+/// Plays back a recording made with a `record` output, keeping the original timing
+pub struct ReplayEffect {
+    frames: Vec<Frame>,
+    /// When each frame is shown, relative to the start of a play through
+    times: Vec<Duration>,
+    /// When the current play through started, in effect time
+    start: Duration,
+    next_frame: usize,
+}
+
+impl Effect for ReplayEffect {
+    fn new() -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        let path = &config::get().replay.path;
+        let frames =
+            recording::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+
+        Ok(Self {
+            times: recording::timeline(&frames),
+            frames,
+            start: Duration::ZERO,
+            next_frame: 0,
+        })
+    }
+
+    fn update(&mut self, ctx: &FrameContext) -> anyhow::Result<Option<LedData>> {
+        if self.frames.is_empty() {
+            anyhow::bail!("the recording is empty");
+        }
+
+        if self.next_frame >= self.frames.len() {
+            if !config::get().replay.repeat {
+                return Ok(None);
+            }
+
+            self.start = ctx.time;
+            self.next_frame = 0;
+        }
+
+        // Skip to the latest frame that is due, dropping any that were missed
+        let elapsed = ctx.time - self.start;
+        let due = self.times[self.next_frame..]
+            .iter()
+            .take_while(|time| **time <= elapsed)
+            .count();
+
+        if due == 0 {
+            return Ok(None);
+        }
+
+        self.next_frame += due;
+
+        let mut data = self.frames[self.next_frame - 1].data.clone();
+        data.resize(ctx.led_count, Color::BLACK);
+
+        Ok(Some(data))
+    }
+}
@@ -0,0 +1,86 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{config, Color, Effect, EffectConfig, FrameContext, LedData};
+
+/// The latest frame pushed by a client, shared between the scene and its StreamEffect
+#[derive(Default)]
+pub struct Inbox {
+    frame: Option<LedData>,
+    last_push: Option<Instant>,
+}
+
+impl Inbox {
+    /// Queues a frame to be shown, replacing any that has not been shown yet
+    pub fn push(&mut self, frame: LedData) {
+        self.frame = Some(frame);
+        self.last_push = Some(Instant::now());
+    }
+
+    /// How long it has been since a frame was pushed
+    pub fn idle_for(&self) -> Option<Duration> {
+        self.last_push.map(|last_push| last_push.elapsed())
+    }
+}
+
+/// Shows frames pushed over D-Bus by other programs
+pub struct StreamEffect {
+    inbox: Arc<Mutex<Inbox>>,
+}
+
+impl StreamEffect {
+    pub fn with_inbox(inbox: Arc<Mutex<Inbox>>) -> Self {
+        Self { inbox }
+    }
+}
+
+impl Effect for StreamEffect {
+    /// Only the scene can start it, as it owns the inbox frames are pushed to
+    fn new() -> anyhow::Result<Self> {
+        anyhow::bail!("the stream effect starts when a frame is pushed with PushFrame")
+    }
+
+    fn update(&mut self, ctx: &FrameContext) -> anyhow::Result<Option<LedData>> {
+        let frame = self.inbox.lock().unwrap().frame.take();
+
+        Ok(frame.map(|mut frame| {
+            frame.resize(ctx.led_count, Color::BLACK);
+            frame
+        }))
+    }
+
+    fn get_config(&self) -> EffectConfig {
+        EffectConfig {
+            fps: config::get().stream.fps,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shows_the_latest_frame() {
+        let ctx = FrameContext {
+            delta: Duration::ZERO,
+            time: Duration::ZERO,
+            frame: 0,
+            led_count: 3,
+        };
+        let inbox = Arc::new(Mutex::new(Inbox::default()));
+        let mut effect = StreamEffect::with_inbox(inbox.clone());
+
+        inbox.lock().unwrap().push(vec![Color::RED]);
+        inbox.lock().unwrap().push(vec![Color::BLUE, Color::GREEN]);
+
+        assert_eq!(
+            effect.update(&ctx).unwrap(),
+            Some(vec![Color::BLUE, Color::GREEN, Color::BLACK])
+        );
+        assert_eq!(effect.update(&ctx).unwrap(), None);
+        assert!(inbox.lock().unwrap().idle_for().is_some());
+    }
+}
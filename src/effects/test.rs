@@ -1,14 +1,12 @@
-use std::{iter, time::Duration};
-
-use crate::{helpers::vec_to_led_data, Color, Effect, LedData, LED_SIZE};
+use crate::{Color, Effect, FrameContext, LedData};
 pub struct TestEffect {
     iterator: usize,
 }
 impl Effect for TestEffect {
-    fn new() -> Self {
-        Self { iterator: 0 }
+    fn new() -> anyhow::Result<Self> {
+        Ok(Self { iterator: 0 })
     }
-    fn update(&mut self) -> anyhow::Result<Option<LedData>> {
+    fn update(&mut self, ctx: &FrameContext) -> anyhow::Result<Option<LedData>> {
         self.iterator += 1;
         let colour = match self.iterator {
             1 => Color::RED,
@@ -20,14 +18,10 @@ impl Effect for TestEffect {
             }
         };
 
-        Ok(Some(vec_to_led_data(
-            iter::repeat(colour).take(LED_SIZE).collect(),
-        )))
+        Ok(Some(vec![colour; ctx.led_count]))
     }
 
     fn get_config(&self) -> crate::EffectConfig {
-        crate::EffectConfig {
-            delay: Duration::from_secs(1),
-        }
+        crate::EffectConfig { fps: 1.0 }
     }
 }
@@ -1,19 +1,15 @@
-use std::{
-    iter,
-    time::{Duration, Instant},
-};
+use std::time::{Duration, Instant};
 
+use anyhow::Context;
 use evdev::{Device, InputEventKind};
 use nix::sys::epoll::EpollFlags;
 
-use crate::{
-    helpers::{get_random_color, vec_to_led_data},
-    Color, Effect, LED_SIZE,
-};
+use crate::parameters::{Parameter, ParameterKind, ParameterValue, Parameters};
+use crate::{helpers::get_random_color, Color, Effect, FrameContext};
 
 const EVENT_KEY: &str = "/dev/input/event3";
 
-const RIPPLE_TIME: Duration = Duration::from_secs(1);
+const RIPPLE_TIME: &str = "ripple_time";
 
 #[derive(Debug)]
 struct Ripple {
@@ -24,10 +20,11 @@ struct Ripple {
 pub struct TypingRippleEffect {
     device: Device,
     ripples: Vec<Ripple>,
+    parameters: Parameters,
 }
 
 impl Effect for TypingRippleEffect {
-    fn update(&mut self) -> anyhow::Result<Option<crate::LedData>> {
+    fn update(&mut self, ctx: &FrameContext) -> anyhow::Result<Option<crate::LedData>> {
         if let Ok(events) = self.device.fetch_events() {
             let events: Vec<_> = events.collect();
             for event in events {
@@ -44,42 +41,64 @@ impl Effect for TypingRippleEffect {
             }
         }
 
+        let ripple_time = Duration::from_secs_f64(self.parameters.float(RIPPLE_TIME));
+
         self.ripples
-            .retain(|ripple| ripple.start_time.elapsed() < RIPPLE_TIME);
+            .retain(|ripple| ripple.start_time.elapsed() < ripple_time);
 
         let positions: Vec<_> = self
             .ripples
             .iter()
             .map(|r| {
-                ((r.start_time.elapsed().as_millis() as f32 / RIPPLE_TIME.as_millis() as f32)
-                    * LED_SIZE as f32) as usize
+                ((r.start_time.elapsed().as_millis() as f32 / ripple_time.as_millis() as f32)
+                    * ctx.led_count as f32) as usize
             })
             .collect();
 
         let colours: Vec<_> = self.ripples.iter().map(|ripple| ripple.colour).collect();
 
-        let mut data: Vec<Color> = iter::repeat(Color::BLACK).take(LED_SIZE).collect();
-        for (pos, colour) in positions.iter().take(LED_SIZE).zip(colours.iter()) {
-            data[*pos] = *colour;
+        let mut data: Vec<Color> = vec![Color::BLACK; ctx.led_count];
+        for (pos, colour) in positions.iter().zip(colours.iter()) {
+            if let Some(led) = data.get_mut(*pos) {
+                *led = *colour;
+            }
         }
 
         data.reverse();
 
-        Ok(Some(vec_to_led_data(data)))
+        Ok(Some(data))
     }
 
-    fn new() -> Self
+    fn new() -> anyhow::Result<Self>
     where
         Self: Sized,
     {
-        let device = Device::open(EVENT_KEY).unwrap();
+        let device =
+            Device::open(EVENT_KEY).with_context(|| format!("failed to open {}", EVENT_KEY))?;
 
-        set_non_blocking(&device).unwrap();
+        set_non_blocking(&device)?;
 
-        Self {
+        Ok(Self {
             device,
             ripples: Vec::new(),
-        }
+            parameters: Parameters::new(vec![Parameter {
+                name: RIPPLE_TIME,
+                description: "Seconds a ripple takes to cross the strip",
+                kind: ParameterKind::Float {
+                    min: 0.05,
+                    max: 60.0,
+                },
+                default: ParameterValue::Float(1.0),
+            }]),
+        })
+    }
+
+    fn parameters(&self) -> Option<&Parameters> {
+        Some(&self.parameters)
+    }
+
+    fn parameters_mut(&mut self) -> Option<&mut Parameters> {
+        Some(&mut self.parameters)
     }
 }
 
@@ -1,6 +1,6 @@
 use rand::{rngs::ThreadRng, seq::SliceRandom, thread_rng, Rng};
 
-use crate::{Color, LedData, LED_SIZE};
+use crate::Color;
 
 /// Gets a "led strip friendly" random color
 pub fn get_random_color() -> Color {
@@ -19,9 +19,3 @@ pub fn get_random_color_with_rng(rng: &mut ThreadRng) -> Color {
     channels.shuffle(rng);
     Color::new(channels[0], channels[1], channels[2])
 }
-
-pub fn vec_to_led_data(data: Vec<Color>) -> LedData {
-    assert_eq!(data.len(), LED_SIZE);
-
-    data.try_into().unwrap()
-}
@@ -1,29 +1,50 @@
+mod compositor;
+mod config;
 mod controller;
+mod correction;
 mod daemon;
 mod effects;
 pub mod helpers;
-
+mod overlay;
+mod parameters;
+mod playlist;
+mod power;
+mod recording;
+mod recovery;
+mod render;
+mod scene;
+mod schedule;
+mod sink;
+mod sun;
+mod transition;
+
+use crate::compositor::BlendMode;
 use crate::controller::controller;
 use crate::daemon::daemon;
+use crate::parameters::Parameters;
+use crate::transition::TransitionKind;
 
 use clap::Parser;
 use effects::*;
 use fs4::FileExt;
 
-use fuzzy_match::fuzzy_match;
+use fuzzy_match::algorithms::{Levenshtein, SimilarityAlgorithm};
 use serde::{Deserialize, Serialize};
 use strum_macros::Display;
+use thiserror::Error;
 
 use anyhow::Result;
 
 use std::fs::OpenOptions;
+use std::str::FromStr;
 use std::time::Duration;
 
-const UNIVERSE: u16 = 1;
-
-const LED_SIZE: usize = 170;
+type LedData = Vec<Color>;
 
-type LedData = [Color; LED_SIZE];
+/// The number of leds on the strip, as set in the config
+fn led_count() -> usize {
+    config::get().strip.led_count
+}
 
 #[derive(Parser, Clone)]
 #[command(
@@ -53,6 +74,73 @@ pub struct Args {
     test: bool,
     #[arg(short = 'k', long = "kill", help = "Kills the daemon running")]
     kill: bool,
+    #[arg(
+        short = 'b',
+        long = "brightness",
+        help = "Sets the brightness of the whole strip, 0.0 - 1.0"
+    )]
+    brightness: Option<f64>,
+    #[arg(
+        long = "transition",
+        help = "The transition to use with --set-effect: cut, crossfade, wipe or fade_through_black"
+    )]
+    transition: Option<TransitionKind>,
+    #[arg(
+        long = "transition-duration",
+        default_value_t = 500,
+        help = "How long the transition lasts in milliseconds"
+    )]
+    transition_duration: u32,
+    #[arg(
+        long = "add-layer",
+        help = "Draws an effect on top of the current one, see --layer-opacity and --layer-blend"
+    )]
+    add_layer: Option<ClientType>,
+    #[arg(
+        long = "layer-opacity",
+        default_value_t = 1.0,
+        help = "The opacity of the layer added with --add-layer, 0.0 - 1.0"
+    )]
+    layer_opacity: f64,
+    #[arg(
+        long = "layer-blend",
+        default_value_t = BlendMode::Normal,
+        help = "How the layer added with --add-layer is blended: normal, add, multiply, screen or max"
+    )]
+    layer_blend: BlendMode,
+    #[arg(long = "remove-layer", help = "Removes the layer at the given index")]
+    remove_layer: Option<u32>,
+    #[arg(long = "clear-layers", help = "Removes every layer")]
+    clear_layers: bool,
+    #[arg(
+        long = "param",
+        value_name = "KEY=VALUE",
+        help = "Sets a parameter of the current effect, can be repeated"
+    )]
+    params: Vec<String>,
+    #[arg(long = "play-playlist", help = "Plays a playlist from the config")]
+    play_playlist: Option<String>,
+    #[arg(long = "next", help = "Skips to the next effect in the playlist")]
+    next: bool,
+    #[arg(
+        long = "previous",
+        help = "Goes back to the previous effect in the playlist"
+    )]
+    previous: bool,
+    #[arg(
+        long = "pause",
+        help = "Stops updating the effect, leaving the current frame on the strip"
+    )]
+    pause: bool,
+    #[arg(
+        long = "freeze",
+        help = "Leaves the current frame on the strip while the effect keeps running"
+    )]
+    freeze: bool,
+    #[arg(long = "blackout", help = "Turns the strip off and pauses the effect")]
+    blackout: bool,
+    #[arg(long = "resume", help = "Undoes --pause, --freeze or --blackout")]
+    resume: bool,
 }
 
 #[derive(
@@ -73,19 +161,93 @@ pub enum ClientType {
     CodingEffect,
     MusicVisualiserEffect,
     TypingRippleEffect,
+    ReplayEffect,
+    StreamEffect,
 }
 
-impl From<&str> for ClientType {
-    fn from(value: &str) -> Self {
-        let effects = enum_iterator::all::<ClientType>().collect::<Vec<_>>();
-        let effects: Vec<_> = effects.iter().map(|x| (x.to_string(), *x)).collect();
-        let effects: Vec<_> = effects.iter().map(|(s, x)| (s.as_str(), *x)).collect();
-
-        let result = fuzzy_match(value, effects);
-        match result {
-            Some(x) => x,
-            None => panic!("Unknown Effect {}", value),
+/// How similar a name has to be to an effect's to be taken as a typo of it
+const FUZZY_MATCH_THRESHOLD: f32 = 0.8;
+/// How similar a name has to be to an effect's for it to be suggested
+const SUGGESTION_THRESHOLD: f32 = 0.4;
+const MAX_SUGGESTIONS: usize = 3;
+
+#[derive(Debug, Error, PartialEq)]
+#[error("unknown effect {name}{}", did_you_mean(suggestions))]
+pub struct UnknownEffect {
+    pub name: String,
+    /// The closest effects, most similar first
+    pub suggestions: Vec<ClientType>,
+}
+
+fn did_you_mean(suggestions: &[ClientType]) -> String {
+    if suggestions.is_empty() {
+        return String::new();
+    }
+
+    let suggestions: Vec<_> = suggestions.iter().map(ToString::to_string).collect();
+    format!(", did you mean {}?", suggestions.join(" or "))
+}
+
+/// Lowercase without separators or the `Effect` suffix, so `typing-ripple` matches
+/// `TypingRippleEffect`
+fn normalise_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect::<String>()
+        .to_lowercase();
+
+    match name.strip_suffix("effect") {
+        Some(stripped) if !stripped.is_empty() => stripped.to_string(),
+        _ => name,
+    }
+}
+
+impl FromStr for ClientType {
+    type Err = UnknownEffect;
+
+    /// Matches a name exactly, by a part of it only one effect has, or by a close enough typo
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let name = normalise_name(value);
+        let effects: Vec<_> = enum_iterator::all::<ClientType>()
+            .map(|client_type| (normalise_name(&client_type.to_string()), client_type))
+            .collect();
+
+        if let Some((_, client_type)) = effects.iter().find(|(effect, _)| *effect == name) {
+            return Ok(*client_type);
+        }
+
+        let containing: Vec<_> = effects
+            .iter()
+            .filter(|(effect, _)| !name.is_empty() && effect.contains(&name))
+            .collect();
+        if let [(_, client_type)] = containing[..] {
+            return Ok(*client_type);
+        }
+
+        let mut algorithm = Levenshtein::new();
+        let mut similar: Vec<_> = effects
+            .iter()
+            .map(|(effect, client_type)| (algorithm.get_similarity(&name, effect), *client_type))
+            .filter(|(similarity, _)| *similarity >= SUGGESTION_THRESHOLD)
+            .collect();
+        similar.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+        if let [(best, client_type), rest @ ..] = &similar[..] {
+            let unique = rest.first().is_none_or(|(next, _)| next < best);
+            if *best >= FUZZY_MATCH_THRESHOLD && unique {
+                return Ok(*client_type);
+            }
         }
+
+        Err(UnknownEffect {
+            name: value.to_string(),
+            suggestions: similar
+                .into_iter()
+                .take(MAX_SUGGESTIONS)
+                .map(|(_, client_type)| client_type)
+                .collect(),
+        })
     }
 }
 
@@ -93,14 +255,15 @@ macro_rules! into_effect {
     ($self:expr, $( $effect:ident ),+) => {
         match $self {
             $(
-            ClientType::$effect => Box::new(<$effect>::new()) as Box<dyn Effect + Send >,
+            ClientType::$effect => Ok(Box::new(<$effect>::new()?) as Box<dyn Effect + Send >),
         )+
         }
     };
 }
 
 impl ClientType {
-    fn into_effect(self) -> Box<dyn Effect + Send> {
+    /// Creates the effect, failing if something it needs, like a device, is unavailable
+    fn into_effect(self) -> Result<Box<dyn Effect + Send>> {
         into_effect![
             self,
             RainbowEffect,
@@ -108,7 +271,9 @@ impl ClientType {
             TestEffect,
             CodingEffect,
             MusicVisualiserEffect,
-            TypingRippleEffect
+            TypingRippleEffect,
+            ReplayEffect,
+            StreamEffect
         ]
     }
 }
@@ -133,24 +298,42 @@ impl From<&str> for WebStatus {
     }
 }
 
-#[derive(Default)]
+#[derive(Clone, Copy)]
 pub struct EffectConfig {
-    delay: Duration,
+    /// Frames rendered per second
+    fps: f32,
+}
+
+/// Timing information for the frame an effect is rendering
+#[derive(Clone, Copy, Debug)]
+pub struct FrameContext {
+    /// Time since the effect's previous frame
+    pub delta: Duration,
+    /// Time since the effect started
+    pub time: Duration,
+    /// Frames the effect has rendered before this one
+    pub frame: u64,
+    pub led_count: usize,
 }
 
 pub trait Effect {
-    fn update(&mut self) -> Result<Option<LedData>>;
+    fn update(&mut self, ctx: &FrameContext) -> Result<Option<LedData>>;
     fn get_config(&self) -> EffectConfig {
-        EffectConfig {
-            delay: Duration::from_millis(10),
-        }
+        EffectConfig { fps: 100.0 }
     }
-    fn new() -> Self
+    /// The knobs the effect can be tuned with while it runs
+    fn parameters(&self) -> Option<&Parameters> {
+        None
+    }
+    fn parameters_mut(&mut self) -> Option<&mut Parameters> {
+        None
+    }
+    fn new() -> Result<Self>
     where
         Self: Sized;
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Color(u8, u8, u8);
 
 impl Color {
@@ -184,6 +367,7 @@ fn check_and_mark_running() -> Result<std::fs::File, std::io::Error> {
         .read(true)
         .write(true)
         .create(true)
+        .truncate(false)
         .open("/home/rugmj/.led-controller/led-controller.lock")
         .unwrap();
 
@@ -214,8 +398,44 @@ async fn main() {
         }
     }
 
-    controller(args).await.unwrap();
+    if let Err(err) = controller(args).await {
+        println!("Error: {}", err);
+        std::process::exit(1);
+    }
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_effect_names() {
+        let parse = |name: &str| name.parse::<ClientType>();
+
+        assert_eq!(parse("RainbowEffect"), Ok(ClientType::RainbowEffect));
+        assert_eq!(parse("rainbow"), Ok(ClientType::RainbowEffect));
+        assert_eq!(parse("typing-ripple"), Ok(ClientType::TypingRippleEffect));
+        assert_eq!(parse("Music"), Ok(ClientType::MusicVisualiserEffect));
+        assert_eq!(
+            parse("musicvisualizer"),
+            Ok(ClientType::MusicVisualiserEffect)
+        );
+        assert_eq!(parse("rainbw"), Ok(ClientType::RainbowEffect));
+    }
+
+    #[test]
+    fn suggests_close_effects() {
+        let err = "rnbow".parse::<ClientType>().unwrap_err();
+        assert_eq!(err.suggestions.first(), Some(&ClientType::RainbowEffect));
+        assert!(err
+            .to_string()
+            .starts_with("unknown effect rnbow, did you mean RainbowEffect"));
+
+        let err = "qqqq".parse::<ClientType>().unwrap_err();
+        assert!(err.suggestions.is_empty());
+        assert_eq!(err.to_string(), "unknown effect qqqq");
+
+        // Part of more than one effect's name
+        assert!("e".parse::<ClientType>().is_err());
+    }
+}
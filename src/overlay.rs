@@ -0,0 +1,175 @@
+use std::{
+    f32::consts::TAU,
+    time::{Duration, Instant},
+};
+
+use crate::{compositor::BlendMode, Color, LedData};
+
+/// How fast the strip is rendered while an overlay is showing
+pub const OVERLAY_FPS: f32 = 60.0;
+
+/// How an overlay animates while it is shown
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OverlayKind {
+    /// On for the first half of each period and off for the second, `count` times
+    Flash { count: u32 },
+    /// Fades in and out once per period until `duration` has passed
+    Pulse { duration: Duration },
+}
+
+/// A short lived colour drawn over a region of the strip, e.g. for a notification
+#[derive(Clone, Copy, Debug)]
+pub struct Overlay {
+    pub color: Color,
+    pub start: usize,
+    /// How many leds are covered, 0 covers every led from `start`
+    pub length: usize,
+    pub period: Duration,
+    pub kind: OverlayKind,
+    /// Overlays with a higher priority are drawn over those with a lower one
+    pub priority: u8,
+}
+
+impl Overlay {
+    fn lifetime(&self) -> Duration {
+        match self.kind {
+            OverlayKind::Flash { count } => self.period * count,
+            OverlayKind::Pulse { duration } => duration,
+        }
+    }
+
+    /// How strongly the overlay covers the strip `elapsed` after it started
+    fn opacity(&self, elapsed: Duration) -> f32 {
+        let phase = (elapsed.as_secs_f32() / self.period.as_secs_f32()).fract();
+
+        match self.kind {
+            OverlayKind::Flash { .. } if phase < 0.5 => 1.0,
+            OverlayKind::Flash { .. } => 0.0,
+            OverlayKind::Pulse { .. } => 0.5 - 0.5 * (phase * TAU).cos(),
+        }
+    }
+
+    fn draw(&self, data: &mut LedData, elapsed: Duration) {
+        let opacity = self.opacity(elapsed);
+        let end = match self.length {
+            0 => data.len(),
+            length => self.start.saturating_add(length),
+        };
+
+        for led in data.iter_mut().take(end).skip(self.start) {
+            *led = BlendMode::Normal.blend(*led, self.color, opacity);
+        }
+    }
+}
+
+/// Overlays drawn over the scene until they expire, lowest priority first
+#[derive(Default)]
+pub struct Overlays {
+    overlays: Vec<(Overlay, Instant)>,
+}
+
+impl Overlays {
+    pub fn is_empty(&self) -> bool {
+        self.overlays.is_empty()
+    }
+
+    /// Shows an overlay from `now`, over any others with the same priority
+    pub fn push(&mut self, overlay: Overlay, now: Instant) {
+        let index = self
+            .overlays
+            .partition_point(|(other, _)| other.priority <= overlay.priority);
+        self.overlays.insert(index, (overlay, now));
+    }
+
+    pub fn clear(&mut self) {
+        self.overlays.clear();
+    }
+
+    /// Drops the overlays that have expired and draws the rest
+    pub fn apply(&mut self, data: &mut LedData, now: Instant) {
+        self.overlays
+            .retain(|(overlay, started)| now.duration_since(*started) < overlay.lifetime());
+
+        for (overlay, started) in &self.overlays {
+            overlay.draw(data, now.duration_since(*started));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flash(color: Color, start: usize, length: usize, priority: u8) -> Overlay {
+        Overlay {
+            color,
+            start,
+            length,
+            period: Duration::from_secs(1),
+            kind: OverlayKind::Flash { count: 2 },
+            priority,
+        }
+    }
+
+    #[test]
+    fn flashes_a_region() {
+        let now = Instant::now();
+        let mut overlays = Overlays::default();
+        overlays.push(flash(Color::RED, 1, 2, 0), now);
+
+        let mut data = vec![Color::BLACK; 4];
+        overlays.apply(&mut data, now);
+        assert_eq!(
+            data,
+            vec![Color::BLACK, Color::RED, Color::RED, Color::BLACK]
+        );
+
+        // Off for the second half of the period
+        let mut data = vec![Color::BLACK; 4];
+        overlays.apply(&mut data, now + Duration::from_millis(750));
+        assert_eq!(data, vec![Color::BLACK; 4]);
+    }
+
+    #[test]
+    fn expires() {
+        let now = Instant::now();
+        let mut overlays = Overlays::default();
+        overlays.push(flash(Color::RED, 0, 0, 0), now);
+
+        let mut data = vec![Color::BLACK; 2];
+        overlays.apply(&mut data, now + Duration::from_secs(1));
+        assert_eq!(data, vec![Color::RED; 2]);
+
+        let mut data = vec![Color::BLACK; 2];
+        overlays.apply(&mut data, now + Duration::from_secs(2));
+        assert_eq!(data, vec![Color::BLACK; 2]);
+        assert!(overlays.is_empty());
+    }
+
+    #[test]
+    fn draws_higher_priorities_on_top() {
+        let now = Instant::now();
+        let mut overlays = Overlays::default();
+        overlays.push(flash(Color::BLUE, 0, 0, 5), now);
+        overlays.push(flash(Color::RED, 0, 0, 1), now);
+        overlays.push(flash(Color::GREEN, 0, 1, 5), now);
+
+        let mut data = vec![Color::BLACK; 2];
+        overlays.apply(&mut data, now);
+        assert_eq!(data, vec![Color::GREEN, Color::BLUE]);
+    }
+
+    #[test]
+    fn pulses() {
+        let overlay = Overlay {
+            kind: OverlayKind::Pulse {
+                duration: Duration::from_secs(10),
+            },
+            ..flash(Color::RED, 0, 0, 0)
+        };
+
+        assert_eq!(overlay.opacity(Duration::ZERO), 0.0);
+        assert!((overlay.opacity(Duration::from_millis(500)) - 1.0).abs() < 1e-6);
+        assert_eq!(overlay.lifetime(), Duration::from_secs(10));
+    }
+}
@@ -0,0 +1,265 @@
+use std::fmt;
+
+use thiserror::Error;
+
+use crate::Color;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ParameterError {
+    #[error("unknown parameter {0}")]
+    Unknown(String),
+    #[error("{name} expects {expected}, got {value}")]
+    Invalid {
+        name: String,
+        value: String,
+        expected: String,
+    },
+}
+
+/// The type of a parameter and the values it accepts
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParameterKind {
+    Float {
+        min: f64,
+        max: f64,
+    },
+    Integer {
+        min: i64,
+        max: i64,
+    },
+    /// A hex colour, `#rrggbb`
+    Color,
+}
+
+impl ParameterKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ParameterKind::Float { .. } => "float",
+            ParameterKind::Integer { .. } => "integer",
+            ParameterKind::Color => "color",
+        }
+    }
+
+    /// The accepted values, empty when every value of the type is accepted
+    pub fn range(&self) -> String {
+        match self {
+            ParameterKind::Float { min, max } => format!("{} - {}", min, max),
+            ParameterKind::Integer { min, max } => format!("{} - {}", min, max),
+            ParameterKind::Color => String::new(),
+        }
+    }
+
+    fn expected(&self) -> String {
+        match self {
+            ParameterKind::Color => "a colour like #ff8000".to_string(),
+            _ => format!("{} {}", self.name(), self.range()),
+        }
+    }
+
+    fn parse(&self, value: &str) -> Option<ParameterValue> {
+        match *self {
+            ParameterKind::Float { min, max } => value
+                .parse()
+                .ok()
+                .filter(|value| (min..=max).contains(value))
+                .map(ParameterValue::Float),
+            ParameterKind::Integer { min, max } => value
+                .parse()
+                .ok()
+                .filter(|value| (min..=max).contains(value))
+                .map(ParameterValue::Integer),
+            ParameterKind::Color => parse_color(value).map(ParameterValue::Color),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParameterValue {
+    Float(f64),
+    Integer(i64),
+    Color(Color),
+}
+
+impl fmt::Display for ParameterValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParameterValue::Float(value) => write!(f, "{}", value),
+            ParameterValue::Integer(value) => write!(f, "{}", value),
+            ParameterValue::Color(Color(r, g, b)) => write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
+        }
+    }
+}
+
+pub fn parse_color(value: &str) -> Option<Color> {
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some(Color::new(channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// A knob an effect exposes, the default must be one of the values `kind` accepts
+#[derive(Clone, Copy, Debug)]
+pub struct Parameter {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub kind: ParameterKind,
+    pub default: ParameterValue,
+}
+
+/// An effect's parameter schema along with their current values
+#[derive(Clone, Debug)]
+pub struct Parameters {
+    parameters: Vec<(Parameter, ParameterValue)>,
+}
+
+impl Parameters {
+    pub fn new(parameters: Vec<Parameter>) -> Self {
+        Self {
+            parameters: parameters
+                .into_iter()
+                .map(|parameter| (parameter, parameter.default))
+                .collect(),
+        }
+    }
+
+    /// Every parameter with its current value
+    pub fn iter(&self) -> impl Iterator<Item = &(Parameter, ParameterValue)> {
+        self.parameters.iter()
+    }
+
+    /// Parses and sets a parameter, leaving it unchanged if the value is not accepted
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), ParameterError> {
+        let (parameter, current) = self
+            .parameters
+            .iter_mut()
+            .find(|(parameter, _)| parameter.name == name)
+            .ok_or_else(|| ParameterError::Unknown(name.to_string()))?;
+
+        *current = parameter
+            .kind
+            .parse(value)
+            .ok_or_else(|| ParameterError::Invalid {
+                name: name.to_string(),
+                value: value.to_string(),
+                expected: parameter.kind.expected(),
+            })?;
+        Ok(())
+    }
+
+    fn get(&self, name: &str) -> ParameterValue {
+        self.parameters
+            .iter()
+            .find(|(parameter, _)| parameter.name == name)
+            .map(|(_, value)| *value)
+            .unwrap_or_else(|| panic!("Effect has no parameter {}", name))
+    }
+
+    pub fn float(&self, name: &str) -> f64 {
+        match self.get(name) {
+            ParameterValue::Float(value) => value,
+            value => panic!("Parameter {} is not a float: {:?}", name, value),
+        }
+    }
+
+    pub fn integer(&self, name: &str) -> i64 {
+        match self.get(name) {
+            ParameterValue::Integer(value) => value,
+            value => panic!("Parameter {} is not an integer: {:?}", name, value),
+        }
+    }
+
+    pub fn color(&self, name: &str) -> Color {
+        match self.get(name) {
+            ParameterValue::Color(value) => value,
+            value => panic!("Parameter {} is not a colour: {:?}", name, value),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    pub fn parameters() -> Parameters {
+        Parameters::new(vec![
+            Parameter {
+                name: "speed",
+                description: "",
+                kind: ParameterKind::Float {
+                    min: 0.0,
+                    max: 10.0,
+                },
+                default: ParameterValue::Float(1.0),
+            },
+            Parameter {
+                name: "size",
+                description: "",
+                kind: ParameterKind::Integer { min: 1, max: 100 },
+                default: ParameterValue::Integer(10),
+            },
+            Parameter {
+                name: "colour",
+                description: "",
+                kind: ParameterKind::Color,
+                default: ParameterValue::Color(Color::BLUE),
+            },
+        ])
+    }
+
+    #[test]
+    fn starts_with_defaults() {
+        let parameters = parameters();
+
+        assert_eq!(parameters.float("speed"), 1.0);
+        assert_eq!(parameters.integer("size"), 10);
+        assert_eq!(parameters.color("colour"), Color::BLUE);
+    }
+
+    #[test]
+    fn sets_values() {
+        let mut parameters = parameters();
+
+        parameters.set("speed", "2.5").unwrap();
+        parameters.set("size", "100").unwrap();
+        parameters.set("colour", "#FF8000").unwrap();
+
+        assert_eq!(parameters.float("speed"), 2.5);
+        assert_eq!(parameters.integer("size"), 100);
+        assert_eq!(parameters.color("colour"), Color::new(255, 128, 0));
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        let mut parameters = parameters();
+
+        assert_eq!(
+            parameters.set("speed", "11"),
+            Err(ParameterError::Invalid {
+                name: "speed".to_string(),
+                value: "11".to_string(),
+                expected: "float 0 - 10".to_string(),
+            })
+        );
+        assert!(parameters.set("size", "1.5").is_err());
+        assert!(parameters.set("colour", "#ff80").is_err());
+        assert!(parameters.set("colour", "#ff80zz").is_err());
+        assert_eq!(
+            parameters.set("nope", "1"),
+            Err(ParameterError::Unknown("nope".to_string()))
+        );
+
+        assert_eq!(parameters.float("speed"), 1.0);
+    }
+
+    #[test]
+    fn displays_values() {
+        assert_eq!(ParameterValue::Float(2.5).to_string(), "2.5");
+        assert_eq!(
+            ParameterValue::Color(Color::new(255, 128, 0)).to_string(),
+            "#ff8000"
+        );
+    }
+}
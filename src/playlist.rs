@@ -0,0 +1,159 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use rand::{seq::SliceRandom, thread_rng};
+
+use crate::{
+    config::{PlaylistConfig, PlaylistEntry},
+    effects::RunningEffect,
+};
+
+/// A playlist being played, tracking which entry is on and when it started
+pub struct Playlist {
+    name: String,
+    entries: Vec<PlaylistEntry>,
+    shuffle: bool,
+    /// Indices into `entries` in the order they are played
+    order: Vec<usize>,
+    position: usize,
+    started: Instant,
+}
+
+impl Playlist {
+    pub fn new(name: &str, config: &PlaylistConfig, now: Instant) -> Self {
+        let mut playlist = Self {
+            name: name.to_string(),
+            entries: config.entries.clone(),
+            shuffle: config.shuffle,
+            order: (0..config.entries.len()).collect(),
+            position: 0,
+            started: now,
+        };
+        playlist.reorder();
+        playlist
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn current(&self) -> &PlaylistEntry {
+        &self.entries[self.order[self.position]]
+    }
+
+    /// Whether the current entry has played for its whole duration
+    pub fn is_due(&self, now: Instant) -> bool {
+        now.duration_since(self.started) >= self.current().duration()
+    }
+
+    /// Moves on to the next entry, starting again from the first after the last
+    pub fn next(&mut self, now: Instant) {
+        self.position += 1;
+        if self.position == self.order.len() {
+            self.position = 0;
+            self.reorder();
+        }
+        self.started = now;
+    }
+
+    pub fn previous(&mut self, now: Instant) {
+        self.position = self.position.checked_sub(1).unwrap_or(self.order.len() - 1);
+        self.started = now;
+    }
+
+    /// Gives the current entry longer to play, e.g. for the time it was paused
+    pub fn delay(&mut self, by: Duration) {
+        self.started += by;
+    }
+
+    fn reorder(&mut self) {
+        if self.shuffle {
+            self.order.shuffle(&mut thread_rng());
+        }
+    }
+}
+
+/// Creates an entry's effect with its parameters set, this can block so it is never called
+/// with the scene locked
+pub fn start_entry(playlist: &str, entry: &PlaylistEntry) -> Result<RunningEffect> {
+    let effect = entry
+        .effect
+        .into_effect()
+        .with_context(|| format!("failed to start {}", entry.effect))?;
+    let mut effect = RunningEffect::new(effect);
+
+    for (name, value) in entry.params() {
+        if let Err(err) = effect.set_parameter(name, &value) {
+            println!(
+                "Error setting parameter {} of {} in {}: {:#}",
+                name, entry.effect, playlist, err
+            );
+        }
+    }
+
+    Ok(effect)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::ClientType;
+
+    fn playlist(shuffle: bool) -> PlaylistConfig {
+        let entry = |effect, duration_s| PlaylistEntry {
+            effect,
+            duration_s,
+            params: BTreeMap::new(),
+        };
+
+        PlaylistConfig {
+            shuffle,
+            entries: vec![
+                entry(ClientType::RainbowEffect, 300),
+                entry(ClientType::RandomEffect, 30),
+                entry(ClientType::TestEffect, 10),
+            ],
+        }
+    }
+
+    #[test]
+    fn plays_entries_in_order() {
+        let start = Instant::now();
+        let mut playlist = Playlist::new("evening", &playlist(false), start);
+        assert_eq!(playlist.current().effect, ClientType::RainbowEffect);
+
+        assert!(!playlist.is_due(start + Duration::from_secs(299)));
+        assert!(playlist.is_due(start + Duration::from_secs(300)));
+
+        let later = start + Duration::from_secs(300);
+        playlist.next(later);
+        assert_eq!(playlist.current().effect, ClientType::RandomEffect);
+        assert!(!playlist.is_due(later));
+
+        playlist.next(later);
+        playlist.next(later);
+        assert_eq!(playlist.current().effect, ClientType::RainbowEffect);
+
+        playlist.previous(later);
+        assert_eq!(playlist.current().effect, ClientType::TestEffect);
+    }
+
+    #[test]
+    fn shuffles_every_entry() {
+        let start = Instant::now();
+        let mut playlist = Playlist::new("evening", &playlist(true), start);
+
+        let mut effects: Vec<_> = (0..3)
+            .map(|_| {
+                let effect = playlist.current().effect;
+                playlist.next(start);
+                effect as usize
+            })
+            .collect();
+        effects.sort();
+
+        assert_eq!(effects, [0, 1, 2]);
+    }
+}
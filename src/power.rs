@@ -0,0 +1,75 @@
+use crate::{config::PowerConfig, LedData};
+
+/// Estimates the current the strip draws showing `data`, in milliamps
+pub fn estimate_milliamps(data: &LedData, config: &PowerConfig) -> f32 {
+    let channels: u32 = data
+        .iter()
+        .map(|c| c.0 as u32 + c.1 as u32 + c.2 as u32)
+        .sum();
+
+    channels as f32 / 255.0 * config.milliamps_per_channel
+        + data.len() as f32 * config.idle_milliamps_per_led
+}
+
+/// Scales the frame down so the strip stays within the power budget, returning the scale used
+pub fn limit(data: &mut LedData, config: &PowerConfig) -> f32 {
+    let Some(budget) = config.budget_milliamps else {
+        return 1.0;
+    };
+
+    let estimate = estimate_milliamps(data, config);
+    if estimate <= budget {
+        return 1.0;
+    }
+
+    // The idle draw can't be scaled away, so only the colour channels count towards the scale
+    let idle = data.len() as f32 * config.idle_milliamps_per_led;
+    let scale = ((budget - idle) / (estimate - idle)).clamp(0.0, 1.0);
+
+    for c in data.iter_mut() {
+        c.brightness(scale);
+    }
+
+    scale
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+
+    fn config(budget_milliamps: Option<f32>) -> PowerConfig {
+        PowerConfig {
+            milliamps_per_channel: 20.0,
+            idle_milliamps_per_led: 1.0,
+            budget_milliamps,
+            ..PowerConfig::default()
+        }
+    }
+
+    #[test]
+    fn estimates_current() {
+        let data = vec![Color::WHITE, Color::RED, Color::BLACK];
+        assert_eq!(estimate_milliamps(&data, &config(None)), 83.0);
+    }
+
+    #[test]
+    fn scales_frames_over_budget() {
+        let mut data = vec![Color::WHITE; 10];
+
+        let scale = limit(&mut data, &config(Some(310.0)));
+
+        assert_eq!(scale, 0.5);
+        assert_eq!(data[0].0, 127);
+        assert!(estimate_milliamps(&data, &config(None)) <= 310.0);
+    }
+
+    #[test]
+    fn leaves_frames_within_budget() {
+        let mut data = vec![Color::RED; 10];
+
+        assert_eq!(limit(&mut data, &config(Some(1000.0))), 1.0);
+        assert_eq!(limit(&mut data, &config(None)), 1.0);
+        assert_eq!(data[0].0, 255);
+    }
+}
@@ -0,0 +1,280 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{Color, LedData};
+
+/// Identifies a recording file, followed by the format version
+const MAGIC: &[u8; 8] = b"LEDREC\0\x01";
+
+/// Written in place of a frame's time each time a recorder opens the file
+const SESSION: u64 = u64::MAX;
+
+/// More leds than any strip has, a frame claiming more is from a corrupt file and is not
+/// allocated
+const MAX_LEDS: u32 = 1 << 20;
+
+/// A single recorded frame
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// When the frame was sent, since the unix epoch
+    pub time: Duration,
+    pub data: LedData,
+    /// The first frame since the recording was opened again, e.g. by the daemon restarting
+    pub new_session: bool,
+}
+
+/// Appends frames to a recording file
+///
+/// Each frame is stored as the send time in microseconds since the unix epoch (u64), the number
+/// of leds (u32) and then the RGB bytes of every led, all little endian. Each time the file is
+/// opened a marker is written, a time of `u64::MAX` with no leds, so the time between sessions is
+/// not played back
+pub struct Recorder {
+    writer: BufWriter<File>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        if file.metadata()?.len() == 0 {
+            file.write_all(MAGIC)?;
+        }
+
+        let mut recorder = Self {
+            writer: BufWriter::new(file),
+        };
+        recorder.write_time(SESSION, &Vec::new())?;
+        Ok(recorder)
+    }
+
+    pub fn record(&mut self, data: &LedData) -> io::Result<()> {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        self.write(time, data)
+    }
+
+    fn write(&mut self, time: Duration, data: &LedData) -> io::Result<()> {
+        self.write_time(time.as_micros() as u64, data)
+    }
+
+    fn write_time(&mut self, time: u64, data: &LedData) -> io::Result<()> {
+        self.writer.write_all(&time.to_le_bytes())?;
+        self.writer.write_all(&(data.len() as u32).to_le_bytes())?;
+        for c in data {
+            self.writer.write_all(&[c.0, c.1, c.2])?;
+        }
+
+        // Flush every frame so a crash still leaves a usable recording
+        self.writer.flush()
+    }
+}
+
+/// Reads every frame from a recording file
+pub fn read(path: &Path) -> io::Result<Vec<Frame>> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let mut magic = [0; MAGIC.len()];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is not a recording", path.display()),
+        ));
+    }
+
+    let mut frames = Vec::new();
+    let mut new_session = true;
+    loop {
+        let (time, rgb) = match read_frame(&mut reader, path) {
+            Ok(frame) => frame,
+            // The end of the file, part way through a frame if the daemon stopped while writing it
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err),
+        };
+
+        if time == SESSION {
+            new_session = true;
+            continue;
+        }
+
+        frames.push(Frame {
+            time: Duration::from_micros(time),
+            data: rgb
+                .chunks(3)
+                .map(|c| Color::new(c[0], c[1], c[2]))
+                .collect(),
+            new_session: std::mem::take(&mut new_session),
+        });
+    }
+
+    Ok(frames)
+}
+
+/// When each frame is played back, relative to the first. A new session plays straight after
+/// the one before it rather than after the time between them
+/// Reads a frame's time and RGB bytes
+fn read_frame(reader: &mut impl Read, path: &Path) -> io::Result<(u64, Vec<u8>)> {
+    let mut time = [0; 8];
+    reader.read_exact(&mut time)?;
+
+    let mut len = [0; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len);
+    if len > MAX_LEDS {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} has a frame of {} leds, it is corrupt",
+                path.display(),
+                len
+            ),
+        ));
+    }
+
+    let mut rgb = vec![0; len as usize * 3];
+    reader.read_exact(&mut rgb)?;
+    Ok((u64::from_le_bytes(time), rgb))
+}
+
+pub fn timeline(frames: &[Frame]) -> Vec<Duration> {
+    let mut offset = Duration::ZERO;
+    let mut previous: Option<&Frame> = None;
+
+    frames
+        .iter()
+        .map(|frame| {
+            if let (Some(previous), false) = (previous, frame.new_session) {
+                offset += frame.time.saturating_sub(previous.time);
+            }
+            previous = Some(frame);
+            offset
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, path::PathBuf};
+
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!(
+            "led-controller-{}-{}.ledrec",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn round_trips_frames() {
+        let path = temp_path("round-trip");
+
+        {
+            let mut recorder = Recorder::create(&path).unwrap();
+            recorder
+                .write(Duration::from_millis(5), &vec![Color::RED; 3])
+                .unwrap();
+        }
+        {
+            // Reopening appends rather than starting a new recording
+            let mut recorder = Recorder::create(&path).unwrap();
+            recorder
+                .write(Duration::from_millis(10), &vec![Color::BLUE; 2])
+                .unwrap();
+        }
+
+        let frames = read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].time, Duration::from_millis(5));
+        assert_eq!(frames[0].data.len(), 3);
+        assert_eq!(frames[0].data[2].0, 255);
+        assert_eq!(frames[1].time, Duration::from_millis(10));
+        assert_eq!(frames[1].data[1].2, 255);
+    }
+
+    #[test]
+    fn restarts_timing_each_session() {
+        let path = temp_path("sessions");
+        let hour = Duration::from_secs(3600);
+
+        {
+            let mut recorder = Recorder::create(&path).unwrap();
+            recorder
+                .write(Duration::from_millis(5), &vec![Color::RED; 1])
+                .unwrap();
+            recorder
+                .write(Duration::from_millis(10), &vec![Color::RED; 1])
+                .unwrap();
+        }
+        {
+            let mut recorder = Recorder::create(&path).unwrap();
+            recorder.write(hour, &vec![Color::BLUE; 1]).unwrap();
+            recorder
+                .write(hour + Duration::from_millis(20), &vec![Color::BLUE; 1])
+                .unwrap();
+        }
+
+        let frames = read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let sessions: Vec<_> = frames.iter().map(|frame| frame.new_session).collect();
+        assert_eq!(sessions, vec![true, false, true, false]);
+
+        // The hour between the sessions is not played back
+        let ms = Duration::from_millis;
+        assert_eq!(timeline(&frames), vec![ms(0), ms(5), ms(5), ms(25)]);
+    }
+
+    #[test]
+    fn ends_at_a_truncated_frame() {
+        let path = temp_path("truncated");
+
+        {
+            let mut recorder = Recorder::create(&path).unwrap();
+            recorder
+                .write(Duration::from_millis(5), &vec![Color::RED; 3])
+                .unwrap();
+            recorder
+                .write(Duration::from_millis(10), &vec![Color::BLUE; 3])
+                .unwrap();
+        }
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(file.metadata().unwrap().len() - 2).unwrap();
+
+        let frames = read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].data[0], Color::RED);
+    }
+
+    #[test]
+    fn rejects_a_corrupt_led_count() {
+        let path = temp_path("corrupt");
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(5u64.to_le_bytes());
+        bytes.extend(u32::MAX.to_le_bytes());
+        fs::write(&path, bytes).unwrap();
+
+        let err = read(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,158 @@
+use std::time::Instant;
+
+use serde::Deserialize;
+
+use crate::config::RecoveryConfig;
+
+/// How each attempt at bringing a failing effect back is made
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryAction {
+    /// Keeps updating the same effect
+    Retry,
+    /// Creates the effect again first, for effects that lost a device or connection
+    Reinit,
+}
+
+/// What to do with the current effect this frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Step {
+    Update,
+    /// Still backing off, the effect is left alone
+    Wait,
+    Reinit,
+    Fallback,
+}
+
+/// Tracks the errors of the current effect and decides how to recover from them
+#[derive(Debug, Default)]
+pub struct Recovery {
+    /// Errors since the effect last rendered successfully
+    consecutive: u32,
+    /// Errors since the daemon started
+    total: u64,
+    last_error: Option<String>,
+    retry_at: Option<Instant>,
+}
+
+impl Recovery {
+    pub fn next(&self, now: Instant, config: &RecoveryConfig) -> Step {
+        let Some(retry_at) = self.retry_at else {
+            return Step::Update;
+        };
+
+        if now < retry_at {
+            Step::Wait
+        } else if config
+            .fallback_after
+            .is_some_and(|fallback_after| self.consecutive >= fallback_after)
+        {
+            Step::Fallback
+        } else {
+            match config.action {
+                RecoveryAction::Retry => Step::Update,
+                RecoveryAction::Reinit => Step::Reinit,
+            }
+        }
+    }
+
+    pub fn failed(&mut self, err: &anyhow::Error, now: Instant, config: &RecoveryConfig) {
+        self.consecutive += 1;
+        self.total += 1;
+        self.last_error = Some(format!("{:#}", err));
+        self.retry_at = Some(now + config.backoff(self.consecutive));
+    }
+
+    /// Clears the run of errors, after a successful frame or when the effect is replaced. The
+    /// total and last error are kept
+    pub fn reset(&mut self) {
+        self.consecutive = 0;
+        self.retry_at = None;
+    }
+
+    /// Updates the effect on the next frame without backing off, once it has been replaced by a
+    /// new instance. The errors are kept, so it still falls back if the new one fails too
+    pub fn retry_now(&mut self) {
+        self.retry_at = None;
+    }
+
+    pub fn consecutive(&self) -> u32 {
+        self.consecutive
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn config(action: RecoveryAction, fallback_after: Option<u32>) -> RecoveryConfig {
+        RecoveryConfig {
+            action,
+            backoff_ms: 100,
+            max_backoff_ms: 1000,
+            fallback_after,
+            ..RecoveryConfig::default()
+        }
+    }
+
+    #[test]
+    fn backs_off_after_errors() {
+        let config = config(RecoveryAction::Retry, None);
+        let start = Instant::now();
+        let mut recovery = Recovery::default();
+        assert_eq!(recovery.next(start, &config), Step::Update);
+
+        recovery.failed(&anyhow::anyhow!("lost"), start, &config);
+        assert_eq!(recovery.next(start, &config), Step::Wait);
+        assert_eq!(
+            recovery.next(start + Duration::from_millis(100), &config),
+            Step::Update
+        );
+
+        recovery.failed(&anyhow::anyhow!("lost"), start, &config);
+        assert_eq!(
+            recovery.next(start + Duration::from_millis(100), &config),
+            Step::Wait
+        );
+        assert_eq!(
+            (
+                recovery.consecutive(),
+                recovery.total(),
+                recovery.last_error()
+            ),
+            (2, 2, Some("lost"))
+        );
+
+        recovery.reset();
+        assert_eq!(recovery.next(start, &config), Step::Update);
+        assert_eq!((recovery.consecutive(), recovery.total()), (0, 2));
+    }
+
+    #[test]
+    fn reinitialises_then_falls_back() {
+        let config = config(RecoveryAction::Reinit, Some(2));
+        let start = Instant::now();
+        let later = start + Duration::from_secs(1);
+        let mut recovery = Recovery::default();
+
+        recovery.failed(&anyhow::anyhow!("lost"), start, &config);
+        assert_eq!(recovery.next(later, &config), Step::Reinit);
+
+        // The new instance is updated straight away
+        recovery.retry_now();
+        assert_eq!(recovery.next(start, &config), Step::Update);
+
+        recovery.failed(&anyhow::anyhow!("lost"), start, &config);
+        assert_eq!(recovery.next(later, &config), Step::Fallback);
+    }
+}
@@ -0,0 +1,239 @@
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::Local;
+
+use crate::{
+    config, correction::Correction, playlist::Playlist, power, scene::Scene, schedule::Scheduler,
+    sink, sink::Sink, LedData,
+};
+
+/// Counters describing how well the render loop is keeping up
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RenderStats {
+    /// Frames rendered since the daemon started
+    pub frames: u64,
+    /// Frames skipped because the loop fell behind its deadlines
+    pub skipped: u64,
+    /// The rate frames are being rendered at
+    pub fps: f32,
+}
+
+/// How much each new frame time contributes to the measured fps
+const FPS_SMOOTHING: f32 = 0.05;
+
+/// How often the schedule is checked for a rule that has started
+const SCHEDULE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Ticks at a fixed rate, the next deadline is based on the previous one rather than when the
+/// frame finished so time spent rendering does not slow the effect down
+struct Ticker {
+    next: Instant,
+    last_tick: Option<Instant>,
+}
+
+impl Ticker {
+    fn new() -> Self {
+        Self {
+            next: Instant::now(),
+            last_tick: None,
+        }
+    }
+
+    /// Waits for the next deadline, returning how many deadlines were missed on the way
+    fn wait(&mut self, interval: Duration) -> u64 {
+        let (next, skipped) = next_deadline(self.next, Instant::now(), interval);
+        self.next = next;
+
+        let now = Instant::now();
+        if next > now {
+            thread::sleep(next - now);
+        }
+
+        skipped
+    }
+
+    /// The instantaneous rate of calls to this, in ticks per second
+    fn rate(&mut self) -> Option<f32> {
+        let now = Instant::now();
+        let rate = self
+            .last_tick
+            .map(|last_tick| now.duration_since(last_tick).as_secs_f32())
+            .filter(|elapsed| *elapsed > 0.0)
+            .map(|elapsed| 1.0 / elapsed);
+        self.last_tick = Some(now);
+        rate
+    }
+}
+
+/// The deadline after `previous` and how many deadlines were missed. When the loop has fallen
+/// behind, the missed frames are skipped rather than rendered back to back to catch up, and the
+/// returned deadline is the latest one that has passed so the frame is rendered straight away
+fn next_deadline(previous: Instant, now: Instant, interval: Duration) -> (Instant, u64) {
+    // Nothing can be missed without a gap between frames, and it cannot be divided by
+    if interval.is_zero() {
+        return (now, 0);
+    }
+
+    let next = previous + interval;
+    if next >= now {
+        return (next, 0);
+    }
+
+    let missed = ((now - next).as_nanos() / interval.as_nanos()) as u32;
+    (next + interval * missed, missed as u64)
+}
+
+/// Renders the scene and sends it to every sink, runs on its own thread so the D-Bus
+/// interface on the async runtime is never held up by an effect
+pub struct RenderLoop {
+    pub scene: Arc<Mutex<Scene>>,
+    pub brightness: Arc<Mutex<f32>>,
+    pub stats: Arc<Mutex<RenderStats>>,
+    pub sinks: Vec<Box<dyn Sink + Send>>,
+    pub correction: Correction,
+    pub scheduler: Scheduler,
+}
+
+impl RenderLoop {
+    pub fn spawn(self) -> thread::JoinHandle<()> {
+        thread::Builder::new()
+            .name("render".to_string())
+            .spawn(move || self.run())
+            .expect("failed to spawn the render thread")
+    }
+
+    fn run(mut self) {
+        let mut ticker = Ticker::new();
+        let mut last_frame: Option<LedData> = None;
+        let mut last_brightness = None;
+        let mut last_schedule_check: Option<Instant> = None;
+
+        loop {
+            if last_schedule_check.is_none_or(|check| check.elapsed() >= SCHEDULE_INTERVAL) {
+                last_schedule_check = Some(Instant::now());
+                self.check_schedule();
+            }
+
+            let (fps, data) = {
+                let mut scene = self.scene.lock().unwrap();
+                (scene.get_config().fps, scene.update())
+            };
+
+            let brightness = *self.brightness.lock().unwrap();
+
+            // Effects return None when nothing has changed, the last frame is only sent again
+            // if something after the effect changed it
+            let changed = data.is_some() || last_brightness != Some(brightness);
+            if let Some(data) = data {
+                last_frame = Some(data);
+            }
+            last_brightness = Some(brightness);
+
+            if let (true, Some(frame)) = (changed, &last_frame) {
+                let mut data = frame.clone();
+                self.process(&mut data, brightness);
+                sink::send_all(&mut self.sinks, frame, &data);
+            }
+
+            let interval = Duration::from_secs_f32(1.0 / fps.max(0.01));
+            let skipped = ticker.wait(interval);
+            self.record(&mut ticker, skipped);
+        }
+    }
+
+    /// Applies the scheduled rule that has started since the last check, if any
+    fn check_schedule(&mut self) {
+        let config = &config::get().schedule;
+        let Some(rule) = self.scheduler.update(config, &Local::now()) else {
+            return;
+        };
+
+        if let Some(brightness) = rule.brightness {
+            *self.brightness.lock().unwrap() = brightness;
+        }
+
+        let mut scene = self.scene.lock().unwrap();
+        scene.set_blackout(rule.blackout);
+
+        if let Some(client_type) = rule.effect {
+            scene.stop_playlist();
+            scene.start_effect(client_type);
+        }
+
+        if let Some(name) = &rule.playlist {
+            let playlist = Playlist::new(name, &config::get().playlists[name], Instant::now());
+            scene.start_playlist(playlist);
+        }
+    }
+
+    /// Applies everything that comes between the effect and the sinks
+    fn process(&self, data: &mut LedData, brightness: f32) {
+        for c in data.iter_mut() {
+            c.brightness(brightness);
+        }
+
+        self.correction.apply(data);
+        power::limit(data, &config::get().power);
+    }
+
+    fn record(&self, ticker: &mut Ticker, skipped: u64) {
+        let mut stats = self.stats.lock().unwrap();
+
+        stats.frames += 1;
+        stats.skipped += skipped;
+
+        if let Some(rate) = ticker.rate() {
+            stats.fps += (rate - stats.fps) * FPS_SMOOTHING;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn waits_for_the_next_deadline() {
+        let start = Instant::now();
+
+        // Rendering took 4ms, so only 6ms are left to wait
+        let (next, skipped) = next_deadline(start, start + Duration::from_millis(4), FRAME);
+
+        assert_eq!(next, start + FRAME);
+        assert_eq!(skipped, 0);
+    }
+
+    #[test]
+    fn renders_late_frames_immediately() {
+        let start = Instant::now();
+
+        let (next, skipped) = next_deadline(start, start + Duration::from_millis(15), FRAME);
+
+        assert_eq!(next, start + FRAME);
+        assert_eq!(skipped, 0);
+    }
+
+    #[test]
+    fn skips_missed_frames() {
+        let start = Instant::now();
+
+        let (next, skipped) = next_deadline(start, start + Duration::from_millis(45), FRAME);
+
+        assert_eq!(next, start + FRAME * 4);
+        assert_eq!(skipped, 3);
+    }
+
+    #[test]
+    fn renders_straight_away_without_an_interval() {
+        let start = Instant::now();
+        let now = start + Duration::from_millis(45);
+
+        assert_eq!(next_deadline(start, now, Duration::ZERO), (now, 0));
+    }
+}
@@ -0,0 +1,790 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+
+use crate::{
+    compositor::Compositor,
+    config,
+    effects::{stream::Inbox, RunningEffect, Starting, StreamEffect},
+    led_count,
+    overlay::{Overlays, OVERLAY_FPS},
+    playlist::{self, Playlist},
+    recovery::{Recovery, Step},
+    transition::Transition,
+    ClientType, Color, EffectConfig, LedData,
+};
+
+/// An effect being transitioned away from
+struct Outgoing {
+    effect: RunningEffect,
+    transition: Transition,
+    started: Instant,
+}
+
+/// Why an effect is being created in the background, which decides what is done with it once
+/// it is ready
+enum Start {
+    /// Replaces the current effect with a transition, e.g. the next playlist entry
+    Switch,
+    /// Replaces the failing current effect with a new instance of it
+    Reinit,
+    /// Replaces the failing current effect with the configured fallback
+    Fallback,
+}
+
+/// What was playing before frames were pushed, brought back once they stop
+struct BeforeStream {
+    client_type: ClientType,
+    effect: RunningEffect,
+    playlist: Option<Playlist>,
+    started: Instant,
+}
+
+/// Everything that makes up the frame the render loop sends
+pub struct Scene {
+    client_type: ClientType,
+    current: RunningEffect,
+    outgoing: Option<Outgoing>,
+    layers: Compositor,
+    overlays: Overlays,
+    recovery: Recovery,
+    playlist: Option<Playlist>,
+    /// An effect being created to replace the current one, it keeps running until this is ready
+    starting: Option<(Starting, Start)>,
+    /// Every led is off, the effects are not updated
+    blackout: bool,
+    /// The current frame stays on the strip, the effects are not updated
+    paused: bool,
+    /// The current frame stays on the strip while the effects keep running
+    frozen: bool,
+    /// When the effects stopped being updated, by a pause or blackout
+    halted_at: Option<Instant>,
+    /// The strip is not showing the scene as it is now, e.g. a layer was removed, so the next
+    /// frame is sent even if the effect has not changed
+    refresh: bool,
+    /// The last frame sent before the overlays were drawn over it, which they are drawn over
+    /// while it stays on the strip
+    held: Option<LedData>,
+    /// The last frame sent had overlays on it, so it is sent again once they are gone
+    overlaid: bool,
+    /// What to go back to once frames stop being pushed
+    before_stream: Option<BeforeStream>,
+    /// Where pushed frames wait for the stream effect to show them
+    inbox: Arc<Mutex<Inbox>>,
+}
+
+impl Scene {
+    pub fn new(client_type: ClientType, effect: RunningEffect) -> Self {
+        Self {
+            client_type,
+            current: effect,
+            outgoing: None,
+            layers: Compositor::default(),
+            overlays: Overlays::default(),
+            recovery: Recovery::default(),
+            playlist: None,
+            starting: None,
+            blackout: false,
+            paused: false,
+            frozen: false,
+            halted_at: None,
+            refresh: false,
+            held: None,
+            overlaid: false,
+            before_stream: None,
+            inbox: Arc::default(),
+        }
+    }
+
+    /// Replaces the current effect, both effects keep rendering until the transition finishes
+    pub fn set_effect(
+        &mut self,
+        client_type: ClientType,
+        effect: RunningEffect,
+        transition: Transition,
+    ) {
+        self.client_type = client_type;
+        self.before_stream = None;
+        self.starting = None;
+        self.recovery.reset();
+        let outgoing = std::mem::replace(&mut self.current, effect);
+
+        self.outgoing = (!transition.duration.is_zero()).then(|| Outgoing {
+            effect: outgoing,
+            transition,
+            started: Instant::now(),
+        });
+    }
+
+    /// Starts playing a playlist whose first entry has already been created
+    pub fn play_playlist(&mut self, playlist: Playlist, effect: RunningEffect) {
+        let client_type = playlist.current().effect;
+        self.set_effect(client_type, effect, config::get().transition.transition());
+        self.playlist = Some(playlist);
+    }
+
+    /// Starts playing a playlist, switching to its first entry once it has been created
+    pub fn start_playlist(&mut self, playlist: Playlist) {
+        self.playlist = Some(playlist);
+        self.start_playlist_entry();
+    }
+
+    /// Stops the playlist, leaving its current effect running
+    pub fn stop_playlist(&mut self) {
+        self.playlist = None;
+
+        if matches!(self.starting, Some((_, Start::Switch))) {
+            self.starting = None;
+        }
+    }
+
+    /// Switches to an effect once it has been created in the background
+    pub fn start_effect(&mut self, client_type: ClientType) {
+        let starting = Starting::spawn(client_type, move || {
+            let effect = client_type
+                .into_effect()
+                .with_context(|| format!("failed to start {}", client_type))?;
+            Ok(RunningEffect::new(effect))
+        });
+        self.starting = Some((starting, Start::Switch));
+    }
+
+    pub fn playlist_mut(&mut self) -> Option<&mut Playlist> {
+        self.playlist.as_mut()
+    }
+
+    /// Creates the playlist's current entry in the background, if it cannot be created the
+    /// previous effect keeps running until the next entry is due
+    fn start_playlist_entry(&mut self) {
+        let Some(playlist) = &self.playlist else {
+            return;
+        };
+        let name = playlist.name().to_string();
+        let entry = playlist.current().clone();
+
+        let starting = Starting::spawn(entry.effect, move || playlist::start_entry(&name, &entry));
+        self.starting = Some((starting, Start::Switch));
+    }
+
+    /// Puts the effect being created in place once it is ready
+    fn finish_starting(&mut self) {
+        let Some(result) = self
+            .starting
+            .as_ref()
+            .and_then(|(starting, _)| starting.poll())
+        else {
+            return;
+        };
+        let (starting, start) = self.starting.take().unwrap();
+        let client_type = starting.client_type();
+        let now = Instant::now();
+
+        match (result, start) {
+            (Ok(effect), Start::Reinit) => {
+                self.current = effect;
+                self.recovery.retry_now();
+            }
+            (Ok(effect), Start::Switch | Start::Fallback) => {
+                self.set_effect(client_type, effect, config::get().transition.transition())
+            }
+            (Err(err), Start::Switch) => println!("Error switching effects: {:#}", err),
+            (Err(err), Start::Reinit | Start::Fallback) => self.failed(err, now),
+        }
+    }
+
+    pub fn client_type(&self) -> ClientType {
+        self.client_type
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Queues a frame for the stream effect, switching to it if it is not already running
+    pub fn push_frame(&mut self, frame: LedData) {
+        self.inbox.lock().unwrap().push(frame);
+        self.start_stream();
+    }
+
+    /// Switches to the stream effect straight away, keeping the current effect and playlist to
+    /// go back to
+    fn start_stream(&mut self) {
+        if self.client_type == ClientType::StreamEffect {
+            return;
+        }
+
+        let stream = RunningEffect::new(Box::new(StreamEffect::with_inbox(self.inbox.clone())));
+        let before = BeforeStream {
+            client_type: self.client_type,
+            effect: std::mem::replace(&mut self.current, stream),
+            playlist: self.playlist.take(),
+            started: Instant::now(),
+        };
+
+        self.client_type = ClientType::StreamEffect;
+        self.outgoing = None;
+        self.starting = None;
+        self.recovery.reset();
+        self.before_stream = Some(before);
+    }
+
+    /// Goes back to what was playing before streaming once frames have stopped arriving
+    fn end_idle_stream(&mut self) {
+        let timeout = config::get().stream.idle_timeout();
+        if self.before_stream.is_some()
+            && self
+                .inbox
+                .lock()
+                .unwrap()
+                .idle_for()
+                .is_some_and(|idle| idle >= timeout)
+        {
+            self.end_stream(config::get().transition.transition());
+        }
+    }
+
+    fn end_stream(&mut self, transition: Transition) {
+        let Some(before) = self.before_stream.take() else {
+            return;
+        };
+
+        // Neither was updated while streaming, so they carry on from where they were
+        let mut effect = before.effect;
+        effect.resume();
+        let playlist = before.playlist.map(|mut playlist| {
+            playlist.delay(before.started.elapsed());
+            playlist
+        });
+
+        self.set_effect(before.client_type, effect, transition);
+        self.playlist = playlist;
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    pub fn set_frozen(&mut self, frozen: bool) {
+        self.frozen = frozen;
+    }
+
+    pub fn is_blackout(&self) -> bool {
+        self.blackout
+    }
+
+    pub fn set_blackout(&mut self, blackout: bool) {
+        self.blackout = blackout;
+    }
+
+    /// Undoes a pause, freeze or blackout
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.frozen = false;
+        self.blackout = false;
+    }
+
+    pub fn recovery(&self) -> &Recovery {
+        &self.recovery
+    }
+
+    pub fn current(&self) -> &RunningEffect {
+        &self.current
+    }
+
+    pub fn current_mut(&mut self) -> &mut RunningEffect {
+        &mut self.current
+    }
+
+    pub fn layers(&self) -> &Compositor {
+        &self.layers
+    }
+
+    /// The layers, the frame is sent again afterwards in case one was removed
+    pub fn layers_mut(&mut self) -> &mut Compositor {
+        self.refresh = true;
+        &mut self.layers
+    }
+
+    pub fn overlays_mut(&mut self) -> &mut Overlays {
+        &mut self.overlays
+    }
+
+    pub fn get_config(&self) -> EffectConfig {
+        let mut config = self.current.get_config();
+
+        if let Some(outgoing) = &self.outgoing {
+            config.fps = config.fps.max(outgoing.effect.get_config().fps);
+        }
+
+        if let Some(fps) = self.layers.fps() {
+            config.fps = config.fps.max(fps);
+        }
+
+        if !self.overlays.is_empty() {
+            config.fps = config.fps.max(OVERLAY_FPS);
+        }
+
+        config
+    }
+
+    /// The next frame, or None if nothing has changed since the last one
+    pub fn update(&mut self) -> Option<LedData> {
+        let data = self.update_frame();
+        if let Some(data) = &data {
+            self.held = Some(data.clone());
+        }
+
+        if self.overlays.is_empty() && !self.overlaid {
+            return data;
+        }
+
+        // Overlays change even when the frame below them has not, including while it is held by
+        // a pause, freeze or blackout, and once they are gone it is sent again without them
+        let mut data = data
+            .or_else(|| self.held.clone())
+            .unwrap_or_else(|| vec![Color::BLACK; led_count()]);
+        self.overlays.apply(&mut data, Instant::now());
+        self.overlaid = !self.overlays.is_empty();
+
+        Some(data)
+    }
+
+    /// The next frame without the overlays, or None if it has not changed
+    fn update_frame(&mut self) -> Option<LedData> {
+        if self.blackout || self.paused {
+            self.halted_at.get_or_insert_with(Instant::now);
+            self.refresh = true;
+            return self.blackout.then(|| vec![Color::BLACK; led_count()]);
+        }
+
+        if let Some(halted_at) = self.halted_at.take() {
+            self.catch_up(halted_at.elapsed());
+        }
+
+        let data = self.compose();
+
+        if self.frozen {
+            self.refresh = true;
+            return None;
+        }
+
+        // The effect was already updated by `compose`, only its last frame is needed
+        if std::mem::take(&mut self.refresh) {
+            let data = data
+                .or_else(|| self.current.last_frame().cloned())
+                .unwrap_or_else(|| vec![Color::BLACK; led_count()]);
+            return Some(data);
+        }
+
+        data
+    }
+
+    /// Carries on from where the scene was halted, as if no time had passed
+    fn catch_up(&mut self, halted_for: Duration) {
+        self.current.resume();
+        self.layers.resume();
+
+        if let Some(outgoing) = &mut self.outgoing {
+            outgoing.effect.resume();
+            outgoing.started += halted_for;
+        }
+
+        if let Some(playlist) = &mut self.playlist {
+            playlist.delay(halted_for);
+        }
+    }
+
+    /// Updates the effects and draws the layers over them
+    fn compose(&mut self) -> Option<LedData> {
+        self.end_idle_stream();
+
+        if self
+            .playlist
+            .as_ref()
+            .is_some_and(|playlist| playlist.is_due(Instant::now()))
+        {
+            if let Some(playlist) = &mut self.playlist {
+                playlist.next(Instant::now());
+            }
+            self.start_playlist_entry();
+        }
+        self.finish_starting();
+
+        let data = self.update_base();
+
+        if self.layers.is_empty() {
+            return data;
+        }
+
+        // Layers can change even when the effect below them has not
+        let mut data = data
+            .or_else(|| self.current.last_frame().cloned())
+            .unwrap_or_else(|| vec![Color::BLACK; led_count()]);
+        self.layers.compose(&mut data);
+
+        Some(data)
+    }
+
+    /// Updates the main effect, blending in the outgoing effect during a transition
+    fn update_base(&mut self) -> Option<LedData> {
+        let Some(progress) = self.outgoing.as_ref().map(|outgoing| {
+            outgoing.started.elapsed().as_secs_f32() / outgoing.transition.duration.as_secs_f32()
+        }) else {
+            return self.update_current();
+        };
+
+        // The current effect recovers from errors as it would outside a transition, keeping its
+        // last frame meanwhile
+        let to = self
+            .update_current()
+            .or_else(|| self.current.last_frame().cloned())
+            .unwrap_or_else(|| vec![Color::BLACK; led_count()]);
+
+        let Some(outgoing) = self.outgoing.as_mut().filter(|_| progress < 1.0) else {
+            self.outgoing = None;
+            return Some(to);
+        };
+
+        // A failing outgoing effect keeps its last frame for the rest of the transition
+        let from = outgoing.effect.render();
+
+        Some(outgoing.transition.blend(progress, &from, &to))
+    }
+
+    /// Updates the current effect, recovering it according to the configured policy if it
+    /// fails. The last frame stays on the strip while the effect is failing
+    fn update_current(&mut self) -> Option<LedData> {
+        let config = &config::get().recovery;
+        let now = Instant::now();
+
+        match self.recovery.next(now, config) {
+            Step::Update => {}
+            Step::Wait => return None,
+            // Its thread is still stuck, creating another could leave any number of them behind,
+            // so it is only polled until the update returns or the fallback takes over
+            Step::Reinit if self.current.is_hung() => {}
+            // A replacement is already being created, the last frame stays until it is ready
+            Step::Reinit | Step::Fallback if self.starting.is_some() => return None,
+            Step::Reinit => {
+                let client_type = self.client_type;
+                let parameters = self.current.changed_parameters().to_vec();
+                let starting = Starting::spawn(client_type, move || {
+                    let mut effect = RunningEffect::new(client_type.into_effect()?);
+                    effect.restore_parameters(&parameters);
+                    Ok(effect)
+                });
+                self.starting = Some((starting, Start::Reinit));
+                return None;
+            }
+            Step::Fallback => {
+                println!(
+                    "Falling back to {} after {} errors in {}",
+                    config.fallback,
+                    self.recovery.consecutive(),
+                    self.client_type
+                );
+
+                let fallback = config.fallback;
+                let starting = Starting::spawn(fallback, move || {
+                    Ok(RunningEffect::new(fallback.into_effect()?))
+                });
+                self.starting = Some((starting, Start::Fallback));
+                return None;
+            }
+        }
+
+        match self.current.update() {
+            Ok(data) => {
+                self.recovery.reset();
+                data
+            }
+            Err(err) => {
+                self.failed(err, now);
+                None
+            }
+        }
+    }
+
+    fn failed(&mut self, err: anyhow::Error, now: Instant) {
+        println!("Error in {}: {:#}", self.client_type, err);
+        self.recovery.failed(&err, now, &config::get().recovery);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, sync::mpsc, thread};
+
+    use super::*;
+    use crate::{
+        compositor::{BlendMode, Layer},
+        config::{PlaylistConfig, PlaylistEntry},
+        effects::tests::FakeEffect,
+        overlay::{Overlay, OverlayKind},
+        transition::TransitionKind,
+    };
+
+    const CUT: Transition = Transition {
+        kind: TransitionKind::Cut,
+        duration: Duration::ZERO,
+    };
+
+    /// Updates the scene once its effects have had time to work on the frames asked for
+    fn update(scene: &mut Scene) -> Option<LedData> {
+        thread::sleep(Duration::from_millis(5));
+        scene.update()
+    }
+
+    /// Shows a single colour, only returning a frame the first time
+    fn solid(color: Color) -> RunningEffect {
+        RunningEffect::new(Box::new(FakeEffect {
+            color,
+            once: true,
+            ..FakeEffect::default()
+        }))
+    }
+
+    #[test]
+    fn renders_both_effects_during_a_transition() {
+        let mut scene = Scene::new(ClientType::TestEffect, solid(Color::RED));
+        update(&mut scene).unwrap();
+
+        scene.set_effect(
+            ClientType::TestEffect,
+            solid(Color::BLUE),
+            Transition {
+                kind: TransitionKind::Crossfade,
+                duration: Duration::from_millis(100),
+            },
+        );
+        thread::sleep(Duration::from_millis(50));
+
+        let data = update(&mut scene).unwrap();
+        assert!(data[0].0 > 0 && data[0].2 > 0);
+
+        // Both effects have stopped returning frames, their last frames are still blended
+        let data = update(&mut scene).unwrap();
+        assert!(data[0].0 > 0 && data[0].2 > 0);
+
+        thread::sleep(Duration::from_millis(50));
+
+        let data = update(&mut scene).unwrap();
+        assert_eq!((data[0].0, data[0].2), (0, 255));
+        assert!(scene.outgoing.is_none());
+    }
+
+    #[test]
+    fn draws_layers_over_the_effect() {
+        let mut scene = Scene::new(ClientType::TestEffect, solid(Color::RED));
+        scene.layers_mut().push(Layer {
+            client_type: ClientType::TestEffect,
+            effect: solid(Color::BLUE),
+            opacity: 1.0,
+            blend: BlendMode::Add,
+        });
+
+        let data = update(&mut scene).unwrap();
+        assert_eq!((data[0].0, data[0].2), (255, 255));
+
+        // Neither effect has a new frame, the layers are still composed
+        let data = update(&mut scene).unwrap();
+        assert_eq!((data[0].0, data[0].2), (255, 255));
+
+        // The effect's frame is sent again without the layer
+        scene.layers_mut().clear();
+        assert_eq!(update(&mut scene).unwrap()[0], Color::RED);
+        assert!(update(&mut scene).is_none());
+    }
+
+    #[test]
+    fn clears_overlays_over_an_unchanging_effect() {
+        let mut scene = Scene::new(ClientType::TestEffect, solid(Color::RED));
+        assert_eq!(update(&mut scene).unwrap()[0], Color::RED);
+
+        let overlay = Overlay {
+            color: Color::BLUE,
+            start: 0,
+            length: 0,
+            period: Duration::from_secs(10),
+            kind: OverlayKind::Flash { count: 1 },
+            priority: 0,
+        };
+        scene.overlays_mut().push(overlay, Instant::now());
+        assert_eq!(update(&mut scene).unwrap()[0], Color::BLUE);
+
+        // The effect has no new frame, its last one is sent again without the overlay
+        scene.overlays_mut().clear();
+        assert_eq!(update(&mut scene).unwrap()[0], Color::RED);
+        assert!(update(&mut scene).is_none());
+    }
+
+    #[test]
+    fn draws_overlays_over_a_held_frame() {
+        let mut scene = Scene::new(ClientType::TestEffect, solid(Color::RED));
+        assert_eq!(update(&mut scene).unwrap()[0], Color::RED);
+
+        let overlay = Overlay {
+            color: Color::BLUE,
+            start: 1,
+            length: 1,
+            period: Duration::from_secs(10),
+            kind: OverlayKind::Flash { count: 1 },
+            priority: 0,
+        };
+        scene.set_paused(true);
+        assert!(update(&mut scene).is_none());
+
+        scene.overlays_mut().push(overlay, Instant::now());
+        let data = update(&mut scene).unwrap();
+        assert_eq!((data[0], data[1]), (Color::RED, Color::BLUE));
+
+        scene.overlays_mut().clear();
+        assert_eq!(update(&mut scene).unwrap()[1], Color::RED);
+        assert!(update(&mut scene).is_none());
+
+        scene.set_blackout(true);
+        scene.overlays_mut().push(overlay, Instant::now());
+        let data = update(&mut scene).unwrap();
+        assert_eq!((data[0], data[1]), (Color::BLACK, Color::BLUE));
+    }
+
+    #[test]
+    fn holds_and_restores_the_frame() {
+        let mut scene = Scene::new(ClientType::TestEffect, solid(Color::RED));
+        assert_eq!(update(&mut scene).unwrap()[0], Color::RED);
+
+        scene.set_blackout(true);
+        assert_eq!(update(&mut scene).unwrap()[0], Color::BLACK);
+
+        scene.resume();
+        scene.set_paused(true);
+        assert!(update(&mut scene).is_none());
+        assert!(scene.halted_at.is_some());
+
+        // The effect has no new frame, its last one is sent to replace the blackout
+        scene.resume();
+        assert_eq!(update(&mut scene).unwrap()[0], Color::RED);
+        assert!(scene.halted_at.is_none());
+        assert!(update(&mut scene).is_none());
+
+        scene.set_frozen(true);
+        assert!(update(&mut scene).is_none());
+        scene.resume();
+        assert_eq!(update(&mut scene).unwrap()[0], Color::RED);
+    }
+
+    #[test]
+    fn goes_back_to_the_effect_and_playlist_after_streaming() {
+        let mut scene = Scene::new(ClientType::TestEffect, solid(Color::RED));
+        assert_eq!(update(&mut scene).unwrap()[0], Color::RED);
+
+        let config = PlaylistConfig {
+            shuffle: false,
+            entries: vec![PlaylistEntry {
+                effect: ClientType::TestEffect,
+                duration_s: 60,
+                params: BTreeMap::new(),
+            }],
+        };
+        scene.playlist = Some(Playlist::new("evening", &config, Instant::now()));
+
+        scene.push_frame(vec![Color::BLUE]);
+        assert_eq!(scene.client_type(), ClientType::StreamEffect);
+        assert!(scene.playlist.is_none());
+        assert_eq!(update(&mut scene).unwrap()[0], Color::BLUE);
+
+        scene.end_stream(CUT);
+        assert_eq!(scene.client_type(), ClientType::TestEffect);
+        assert_eq!(scene.playlist.as_ref().unwrap().name(), "evening");
+
+        // The same effect is back, it has no new frame and keeps the one from before
+        assert!(update(&mut scene).is_none());
+        assert_eq!(scene.current().last_frame().unwrap()[0], Color::RED);
+    }
+
+    #[test]
+    fn keeps_rendering_while_the_next_effect_starts() {
+        let mut scene = Scene::new(ClientType::TestEffect, solid(Color::RED));
+        let (started, start) = mpsc::channel();
+        let starting = Starting::spawn(ClientType::TestEffect, move || {
+            start.recv().unwrap();
+            Ok(solid(Color::BLUE))
+        });
+        scene.starting = Some((starting, Start::Switch));
+
+        assert_eq!(update(&mut scene).unwrap()[0], Color::RED);
+
+        started.send(()).unwrap();
+        thread::sleep(Duration::from_millis(5));
+        update(&mut scene);
+        assert!(scene.starting.is_none());
+        assert_eq!(scene.current().last_frame().unwrap()[0], Color::BLUE);
+    }
+
+    #[test]
+    fn cuts_without_a_duration() {
+        let mut scene = Scene::new(ClientType::TestEffect, solid(Color::RED));
+
+        scene.set_effect(
+            ClientType::TestEffect,
+            solid(Color::BLUE),
+            Transition {
+                kind: TransitionKind::Crossfade,
+                duration: Duration::ZERO,
+            },
+        );
+
+        let data = update(&mut scene).unwrap();
+        assert_eq!((data[0].0, data[0].2), (0, 255));
+    }
+
+    #[test]
+    fn backs_off_a_failing_effect() {
+        // Renders a single frame and then fails
+        let failing = RunningEffect::new(Box::new(FakeEffect {
+            fail_from: Some(1),
+            ..FakeEffect::default()
+        }));
+        let mut scene = Scene::new(ClientType::TestEffect, failing);
+
+        assert!(update(&mut scene).is_some());
+        assert!(update(&mut scene).is_none());
+        assert_eq!(scene.recovery().consecutive(), 1);
+        assert_eq!(scene.recovery().last_error(), Some("lost the device"));
+
+        // Not updated again until the backoff has passed
+        assert!(update(&mut scene).is_none());
+        assert_eq!(scene.recovery().consecutive(), 1);
+
+        scene.set_effect(ClientType::TestEffect, solid(Color::BLUE), CUT);
+        assert!(update(&mut scene).is_some());
+        assert_eq!(scene.recovery().consecutive(), 0);
+        assert_eq!(scene.recovery().total(), 1);
+    }
+
+    #[test]
+    fn recovers_an_effect_during_a_transition() {
+        let mut scene = Scene::new(ClientType::TestEffect, solid(Color::RED));
+        let failing = RunningEffect::new(Box::new(FakeEffect {
+            fail_from: Some(1),
+            ..FakeEffect::default()
+        }));
+        let transition = Transition {
+            kind: TransitionKind::Crossfade,
+            duration: Duration::from_secs(60),
+        };
+        scene.set_effect(ClientType::TestEffect, failing, transition);
+
+        assert!(update(&mut scene).is_some());
+        update(&mut scene);
+        assert_eq!(scene.recovery().consecutive(), 1);
+        assert_eq!(scene.recovery().last_error(), Some("lost the device"));
+    }
+}
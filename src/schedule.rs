@@ -0,0 +1,224 @@
+use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Weekday};
+use serde::Deserialize;
+
+use crate::{
+    config::{ScheduleConfig, ScheduleRule},
+    sun,
+};
+
+/// When a schedule rule takes effect, written as `07:30`, `sunrise` or `sunset`, optionally
+/// followed by an offset in minutes like `sunset-30`
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum At {
+    Time(NaiveTime),
+    Sunrise(i64),
+    Sunset(i64),
+}
+
+impl At {
+    pub fn uses_sun(&self) -> bool {
+        !matches!(self, At::Time(_))
+    }
+}
+
+impl TryFrom<String> for At {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let lower = value.trim().to_lowercase();
+
+        for (name, at) in [
+            ("sunrise", At::Sunrise as fn(i64) -> At),
+            ("sunset", At::Sunset),
+        ] {
+            if let Some(offset) = lower.strip_prefix(name) {
+                let offset = offset.trim();
+                let offset = match offset.strip_prefix('+') {
+                    _ if offset.is_empty() => Ok(0),
+                    Some(offset) => offset.trim().parse(),
+                    None => offset.parse(),
+                };
+
+                return offset
+                    .map(at)
+                    .map_err(|_| format!("invalid offset in {}, expected minutes", value));
+            }
+        }
+
+        NaiveTime::parse_from_str(&lower, "%H:%M")
+            .map(At::Time)
+            .map_err(|_| format!("invalid time {}, expected HH:MM, sunrise or sunset", value))
+    }
+}
+
+/// Finds the rule that applies at a given time, the one whose start most recently passed
+pub struct Scheduler {
+    /// The rule that was last applied and when it started, so it is only applied once
+    applied: Option<(usize, i64)>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self { applied: None }
+    }
+
+    /// The rule to apply if a different one has started since the last call
+    pub fn update<'a, Tz: TimeZone>(
+        &mut self,
+        config: &'a ScheduleConfig,
+        now: &DateTime<Tz>,
+    ) -> Option<&'a ScheduleRule> {
+        let (index, start) = active(config, now)?;
+        let key = (index, start.timestamp());
+
+        if self.applied == Some(key) {
+            return None;
+        }
+
+        self.applied = Some(key);
+        Some(&config.rules[index])
+    }
+}
+
+/// The latest rule to have started in the last week, later rules win ties
+fn active<Tz: TimeZone>(
+    config: &ScheduleConfig,
+    now: &DateTime<Tz>,
+) -> Option<(usize, DateTime<Tz>)> {
+    let today = now.date_naive();
+
+    (0..=7)
+        .map(|days| today - Duration::days(days))
+        .flat_map(|date| {
+            config
+                .rules
+                .iter()
+                .enumerate()
+                .filter(move |(_, rule)| applies_on(rule, date.weekday()))
+                .filter_map(move |(index, rule)| Some((index, start(config, rule, date, now)?)))
+        })
+        .filter(|(_, start)| start <= now)
+        .max_by(|(a_index, a), (b_index, b)| a.cmp(b).then(a_index.cmp(b_index)))
+}
+
+fn applies_on(rule: &ScheduleRule, day: Weekday) -> bool {
+    rule.days.is_empty() || rule.days.contains(&day)
+}
+
+/// When the rule starts on `date`, None if there is no such time, like sunset in a polar summer
+fn start<Tz: TimeZone>(
+    config: &ScheduleConfig,
+    rule: &ScheduleRule,
+    date: chrono::NaiveDate,
+    now: &DateTime<Tz>,
+) -> Option<DateTime<Tz>> {
+    let timezone = now.timezone();
+
+    let sun = |offset: i64, sunset: bool| {
+        let (sunrise_time, sunset_time) =
+            sun::sunrise_sunset(date, config.latitude?, config.longitude?)?;
+        let time = if sunset { sunset_time } else { sunrise_time };
+        Some(time.with_timezone(&timezone) + Duration::minutes(offset))
+    };
+
+    match rule.at {
+        At::Time(time) => timezone
+            .from_local_datetime(&date.and_time(time))
+            .earliest(),
+        At::Sunrise(offset) => sun(offset, false),
+        At::Sunset(offset) => sun(offset, true),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, Utc};
+
+    use super::*;
+
+    fn rule(days: Vec<Weekday>, at: &str, brightness: f32) -> ScheduleRule {
+        ScheduleRule {
+            days,
+            at: At::try_from(at.to_string()).unwrap(),
+            effect: None,
+            playlist: None,
+            brightness: Some(brightness),
+            blackout: false,
+        }
+    }
+
+    fn brightness_at<Tz: TimeZone>(config: &ScheduleConfig, now: DateTime<Tz>) -> Option<f32> {
+        active(config, &now).and_then(|(index, _)| config.rules[index].brightness)
+    }
+
+    #[test]
+    fn parses_times() {
+        let parse = |at: &str| At::try_from(at.to_string());
+
+        assert_eq!(
+            parse("07:30"),
+            Ok(At::Time(NaiveTime::from_hms_opt(7, 30, 0).unwrap()))
+        );
+        assert_eq!(parse("Sunrise"), Ok(At::Sunrise(0)));
+        assert_eq!(parse("sunset-30"), Ok(At::Sunset(-30)));
+        assert_eq!(parse("sunrise + 15"), Ok(At::Sunrise(15)));
+        assert!(parse("25:00").is_err());
+        assert!(parse("sunset-soon").is_err());
+    }
+
+    #[test]
+    fn picks_the_latest_rule() {
+        let config = ScheduleConfig {
+            rules: vec![
+                rule(vec![], "08:00", 1.0),
+                rule(vec![], "18:00", 0.4),
+                rule(vec![Weekday::Sat, Weekday::Sun], "10:00", 0.8),
+            ],
+            ..ScheduleConfig::default()
+        };
+
+        // 2024-06-21 is a Friday
+        let at = |day, hour| Utc.with_ymd_and_hms(2024, 6, day, hour, 0, 0).unwrap();
+        assert_eq!(brightness_at(&config, at(21, 7)), Some(0.4));
+        assert_eq!(brightness_at(&config, at(21, 9)), Some(1.0));
+        assert_eq!(brightness_at(&config, at(21, 19)), Some(0.4));
+        assert_eq!(brightness_at(&config, at(22, 9)), Some(1.0));
+        assert_eq!(brightness_at(&config, at(22, 11)), Some(0.8));
+    }
+
+    #[test]
+    fn follows_the_sun() {
+        let config = ScheduleConfig {
+            latitude: Some(51.5074),
+            longitude: Some(-0.1278),
+            rules: vec![rule(vec![], "sunrise", 1.0), rule(vec![], "sunset-30", 0.4)],
+        };
+
+        // British summer time, sunset is at 21:21
+        let bst = FixedOffset::east_opt(3600).unwrap();
+        let at = |hour, minute| bst.with_ymd_and_hms(2024, 6, 21, hour, minute, 0).unwrap();
+        assert_eq!(brightness_at(&config, at(20, 45)), Some(1.0));
+        assert_eq!(brightness_at(&config, at(20, 55)), Some(0.4));
+    }
+
+    #[test]
+    fn applies_each_rule_once() {
+        let config = ScheduleConfig {
+            rules: vec![rule(vec![], "08:00", 1.0), rule(vec![], "18:00", 0.4)],
+            ..ScheduleConfig::default()
+        };
+        let mut scheduler = Scheduler::new();
+        let at = |hour| Utc.with_ymd_and_hms(2024, 6, 21, hour, 0, 0).unwrap();
+
+        assert_eq!(
+            scheduler.update(&config, &at(9)).unwrap().brightness,
+            Some(1.0)
+        );
+        assert!(scheduler.update(&config, &at(10)).is_none());
+        assert_eq!(
+            scheduler.update(&config, &at(18)).unwrap().brightness,
+            Some(0.4)
+        );
+    }
+}
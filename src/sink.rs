@@ -0,0 +1,145 @@
+pub mod artnet;
+pub mod channel_order;
+pub mod preview;
+pub mod record;
+pub mod sacn;
+pub mod wled;
+
+pub use artnet::ArtNetSink;
+pub use channel_order::ChannelOrder;
+pub use preview::PreviewSink;
+pub use record::RecordSink;
+pub use sacn::SacnSink;
+pub use wled::WledSink;
+
+use thiserror::Error;
+
+use crate::{config::OutputConfig, LedData};
+
+#[derive(Debug, Error)]
+pub enum SinkError {
+    #[error("expected a frame of {expected} leds, got {actual}")]
+    IncorrectLength { expected: usize, actual: usize },
+    #[error("sACN error: {0}")]
+    Sacn(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Somewhere rendered frames are sent to
+pub trait Sink {
+    /// A short description of the sink, used when reporting errors
+    fn name(&self) -> String;
+    fn send(&mut self, data: &LedData) -> Result<(), SinkError>;
+    /// Whether the sink is sent frames as the scene rendered them, before brightness, correction
+    /// and power limiting
+    fn before_processing(&self) -> bool {
+        false
+    }
+}
+
+/// Creates the sink described by `config`
+pub fn create_sink(config: &OutputConfig) -> Result<Box<dyn Sink + Send>, SinkError> {
+    Ok(match config {
+        OutputConfig::Sacn(config) => Box::new(SacnSink::new(config)?),
+        OutputConfig::ArtNet(config) => Box::new(ArtNetSink::new(config)?),
+        OutputConfig::Wled(config) => Box::new(WledSink::new(config)?),
+        OutputConfig::Preview(config) => Box::new(PreviewSink::new(config)),
+        OutputConfig::Record(config) => Box::new(RecordSink::new(config)?),
+    })
+}
+
+/// Sends a frame to every sink, a failing sink does not stop the others from receiving it.
+/// `frame` is the frame from the scene and `processed` is what is shown on the strip
+pub fn send_all(sinks: &mut [Box<dyn Sink + Send>], frame: &LedData, processed: &LedData) {
+    for sink in sinks.iter_mut() {
+        let data = match sink.before_processing() {
+            true => frame,
+            false => processed,
+        };
+
+        if let Err(err) = sink.send(data) {
+            println!("Error sending to {}: {}", sink.name(), err);
+        }
+    }
+}
+
+fn check_length(data: &LedData, expected: usize) -> Result<(), SinkError> {
+    if data.len() != expected {
+        return Err(SinkError::IncorrectLength {
+            expected,
+            actual: data.len(),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::Color;
+
+    /// Records every frame it is sent
+    #[derive(Clone, Default)]
+    pub struct FakeSink {
+        pub frames: Arc<Mutex<Vec<LedData>>>,
+        pub fail: bool,
+        pub before_processing: bool,
+    }
+
+    impl Sink for FakeSink {
+        fn name(&self) -> String {
+            "fake".to_string()
+        }
+
+        fn send(&mut self, data: &LedData) -> Result<(), SinkError> {
+            self.frames.lock().unwrap().push(data.clone());
+
+            if self.fail {
+                return Err(SinkError::Sacn("failed".to_string()));
+            }
+            Ok(())
+        }
+
+        fn before_processing(&self) -> bool {
+            self.before_processing
+        }
+    }
+
+    #[test]
+    fn send_all_reaches_every_sink() {
+        let failing = FakeSink {
+            fail: true,
+            ..FakeSink::default()
+        };
+        let working = FakeSink::default();
+
+        let mut sinks: Vec<Box<dyn Sink + Send>> =
+            vec![Box::new(failing.clone()), Box::new(working.clone())];
+
+        let processed = vec![Color::BLACK; 3];
+        send_all(&mut sinks, &vec![Color::RED; 3], &processed);
+        send_all(&mut sinks, &vec![Color::BLUE; 3], &processed);
+
+        assert_eq!(failing.frames.lock().unwrap().len(), 2);
+        let frames = working.frames.lock().unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1], processed);
+    }
+
+    #[test]
+    fn send_all_skips_processing_when_asked() {
+        let raw = FakeSink {
+            before_processing: true,
+            ..FakeSink::default()
+        };
+        let mut sinks: Vec<Box<dyn Sink + Send>> = vec![Box::new(raw.clone())];
+
+        send_all(&mut sinks, &vec![Color::RED; 3], &vec![Color::BLACK; 3]);
+
+        assert_eq!(raw.frames.lock().unwrap()[0], vec![Color::RED; 3]);
+    }
+}
@@ -0,0 +1,144 @@
+use std::net::UdpSocket;
+
+use super::{check_length, Sink, SinkError};
+use crate::{config::ArtNetConfig, led_count, LedData};
+
+const ID: &[u8; 8] = b"Art-Net\0";
+const OP_DMX: u16 = 0x5000;
+const OP_SYNC: u16 = 0x5200;
+const PROTOCOL_VERSION: u16 = 14;
+
+pub struct ArtNetSink {
+    socket: UdpSocket,
+    config: ArtNetConfig,
+    port_addresses: Vec<u16>,
+    sequence: u8,
+}
+
+impl ArtNetSink {
+    pub fn new(config: &ArtNetConfig) -> Result<Self, SinkError> {
+        let socket = UdpSocket::bind(config.bind)?;
+        socket.set_broadcast(true)?;
+
+        Ok(Self {
+            socket,
+            config: config.clone(),
+            port_addresses: config.port_addresses(led_count()),
+            sequence: 0,
+        })
+    }
+
+    /// Sequence numbers run from 1 to 255, 0 would tell the node to ignore ordering
+    fn next_sequence(&mut self) -> u8 {
+        self.sequence = self.sequence.checked_add(1).unwrap_or(1);
+        self.sequence
+    }
+}
+
+impl Sink for ArtNetSink {
+    fn name(&self) -> String {
+        format!("Art-Net {}", self.config.destination())
+    }
+
+    fn send(&mut self, data: &LedData) -> Result<(), SinkError> {
+        check_length(data, led_count())?;
+
+        let sequence = self.next_sequence();
+        for (port_address, pixels) in self
+            .port_addresses
+            .iter()
+            .zip(data.chunks(self.config.channel_order.pixels_per_universe()))
+        {
+            let packet = art_dmx(
+                sequence,
+                *port_address,
+                &self.config.channel_order.to_channels(pixels),
+            );
+            self.socket.send_to(&packet, self.config.destination())?;
+        }
+
+        if self.config.sync {
+            self.socket
+                .send_to(&art_sync(), self.config.destination())?;
+        }
+
+        Ok(())
+    }
+}
+
+fn header(op_code: u16) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(18 + 512);
+    packet.extend_from_slice(ID);
+    packet.extend_from_slice(&op_code.to_le_bytes());
+    packet.extend_from_slice(&PROTOCOL_VERSION.to_be_bytes());
+    packet
+}
+
+/// Builds an ArtDmx packet, the data is padded to the even length the spec requires
+fn art_dmx(sequence: u8, port_address: u16, channels: &[u8]) -> Vec<u8> {
+    let length = channels.len() + channels.len() % 2;
+
+    let mut packet = header(OP_DMX);
+    packet.push(sequence);
+    packet.push(0); // Physical
+    packet.extend_from_slice(&port_address.to_le_bytes()); // SubUni then Net
+    packet.extend_from_slice(&(length as u16).to_be_bytes());
+    packet.extend_from_slice(channels);
+    packet.resize(18 + length, 0);
+    packet
+}
+
+fn art_sync() -> Vec<u8> {
+    let mut packet = header(OP_SYNC);
+    packet.extend_from_slice(&[0, 0]); // Aux1, Aux2
+    packet
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::Ipv4Addr, time::Duration};
+
+    use super::*;
+    use crate::Color;
+
+    #[test]
+    fn encodes_art_dmx() {
+        let packet = art_dmx(7, 0x123, &[1, 2, 3]);
+
+        assert_eq!(&packet[..8], ID);
+        assert_eq!(&packet[8..10], &[0x00, 0x50]);
+        assert_eq!(&packet[10..12], &[0, 14]);
+        assert_eq!(packet[12], 7);
+        assert_eq!(&packet[14..16], &[0x23, 0x01]);
+        assert_eq!(&packet[16..18], &[0, 4]);
+        assert_eq!(&packet[18..], &[1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn sends_to_local_listener() {
+        let listener = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        listener
+            .set_read_timeout(Some(Duration::from_secs(1)))
+            .unwrap();
+
+        let config = ArtNetConfig {
+            destination: Ipv4Addr::LOCALHOST.into(),
+            port: listener.local_addr().unwrap().port(),
+            subnet: 1,
+            sync: true,
+            ..ArtNetConfig::default()
+        };
+        let mut sink = ArtNetSink::new(&config).unwrap();
+
+        sink.send(&vec![Color::RED; led_count()]).unwrap();
+
+        let mut buf = [0; 1024];
+        let len = listener.recv(&mut buf).unwrap();
+        assert_eq!(len, 18 + led_count() * 3);
+        assert_eq!(buf[12], 1);
+        assert_eq!(buf[14], 0x10);
+
+        let len = listener.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], art_sync().as_slice());
+    }
+}
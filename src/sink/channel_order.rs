@@ -0,0 +1,160 @@
+use std::fmt;
+
+use serde::Deserialize;
+
+use crate::Color;
+
+/// The number of channels in a DMX universe
+const DMX_CHANNELS: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Channel {
+    Red,
+    Green,
+    Blue,
+    White,
+}
+
+/// The order a strip expects each pixel's channels in, written like `grb` or `rgbw`
+///
+/// Strips with a white channel get the white shared by all three colours moved onto it
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ChannelOrder(Vec<Channel>);
+
+impl ChannelOrder {
+    pub fn rgb() -> Self {
+        Self(vec![Channel::Red, Channel::Green, Channel::Blue])
+    }
+
+    pub fn brg() -> Self {
+        Self(vec![Channel::Blue, Channel::Red, Channel::Green])
+    }
+
+    pub fn channels_per_pixel(&self) -> usize {
+        self.0.len()
+    }
+
+    /// The number of whole pixels that fit in a DMX universe
+    pub fn pixels_per_universe(&self) -> usize {
+        DMX_CHANNELS / self.channels_per_pixel()
+    }
+
+    fn has_white(&self) -> bool {
+        self.0.contains(&Channel::White)
+    }
+
+    /// Flattens pixels into channels in this order
+    pub fn to_channels(&self, pixels: &[Color]) -> Vec<u8> {
+        let mut channels = Vec::with_capacity(pixels.len() * self.channels_per_pixel());
+
+        for &Color(r, g, b) in pixels {
+            let (r, g, b, w) = if self.has_white() {
+                let w = r.min(g).min(b);
+                (r - w, g - w, b - w, w)
+            } else {
+                (r, g, b, 0)
+            };
+
+            channels.extend(self.0.iter().map(|channel| match channel {
+                Channel::Red => r,
+                Channel::Green => g,
+                Channel::Blue => b,
+                Channel::White => w,
+            }));
+        }
+
+        channels
+    }
+}
+
+impl TryFrom<String> for ChannelOrder {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let channels = value
+            .to_lowercase()
+            .chars()
+            .map(|c| match c {
+                'r' => Ok(Channel::Red),
+                'g' => Ok(Channel::Green),
+                'b' => Ok(Channel::Blue),
+                'w' => Ok(Channel::White),
+                _ => Err(format!(
+                    "unknown channel '{}' in channel order {}",
+                    c, value
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let count = |channel| channels.iter().filter(|&&c| c == channel).count();
+        if count(Channel::Red) != 1
+            || count(Channel::Green) != 1
+            || count(Channel::Blue) != 1
+            || count(Channel::White) > 1
+        {
+            return Err(format!(
+                "channel order {} must contain r, g and b once each and at most one w",
+                value
+            ));
+        }
+
+        Ok(Self(channels))
+    }
+}
+
+impl fmt::Display for ChannelOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for channel in &self.0 {
+            let c = match channel {
+                Channel::Red => 'r',
+                Channel::Green => 'g',
+                Channel::Blue => 'b',
+                Channel::White => 'w',
+            };
+            write!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(value: &str) -> ChannelOrder {
+        ChannelOrder::try_from(value.to_string()).unwrap()
+    }
+
+    #[test]
+    fn reorders_channels() {
+        let pixels = [Color(1, 2, 3), Color(4, 5, 6)];
+
+        assert_eq!(order("rgb").to_channels(&pixels), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(order("GRB").to_channels(&pixels), vec![2, 1, 3, 5, 4, 6]);
+        assert_eq!(
+            ChannelOrder::brg().to_channels(&pixels),
+            vec![3, 1, 2, 6, 4, 5]
+        );
+    }
+
+    #[test]
+    fn extracts_white() {
+        let pixels = [Color(200, 150, 100), Color::WHITE];
+
+        assert_eq!(
+            order("grbw").to_channels(&pixels),
+            vec![50, 100, 0, 100, 0, 0, 0, 255]
+        );
+        assert_eq!(order("rgbw").pixels_per_universe(), 128);
+        assert_eq!(order("bgr").pixels_per_universe(), 170);
+    }
+
+    #[test]
+    fn rejects_invalid_orders() {
+        assert!(ChannelOrder::try_from("rg".to_string()).is_err());
+        assert!(ChannelOrder::try_from("rgbb".to_string()).is_err());
+        assert!(ChannelOrder::try_from("rgbww".to_string()).is_err());
+        assert!(ChannelOrder::try_from("rgx".to_string()).is_err());
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    env,
+    io::{self, Write},
+    time::Instant,
+};
+
+use colored::Colorize;
+
+use super::{Sink, SinkError};
+use crate::{
+    config::{PreviewConfig, PreviewLayout},
+    Color, LedData,
+};
+
+const DEFAULT_WIDTH: usize = 80;
+
+/// How much each new frame time contributes to the fps readout
+const FPS_SMOOTHING: f32 = 0.1;
+
+/// Draws each frame as a row of true colour blocks in the terminal
+pub struct PreviewSink {
+    width: usize,
+    layout: PreviewLayout,
+    show_fps: bool,
+    last_frame: Option<Instant>,
+    fps: f32,
+    lines_drawn: usize,
+}
+
+impl PreviewSink {
+    pub fn new(config: &PreviewConfig) -> Self {
+        colored::control::set_override(true);
+
+        let width = config
+            .width
+            .or_else(|| env::var("COLUMNS").ok()?.parse().ok())
+            .unwrap_or(DEFAULT_WIDTH)
+            .max(1);
+
+        Self {
+            width,
+            layout: config.layout,
+            show_fps: config.show_fps,
+            last_frame: None,
+            fps: 0.0,
+            lines_drawn: 0,
+        }
+    }
+
+    fn update_fps(&mut self) {
+        let now = Instant::now();
+        if let Some(last_frame) = self.last_frame {
+            let elapsed = now.duration_since(last_frame).as_secs_f32();
+            if elapsed > 0.0 {
+                self.fps += (1.0 / elapsed - self.fps) * FPS_SMOOTHING;
+            }
+        }
+        self.last_frame = Some(now);
+    }
+}
+
+impl Sink for PreviewSink {
+    fn name(&self) -> String {
+        "preview".to_string()
+    }
+
+    fn send(&mut self, data: &LedData) -> Result<(), SinkError> {
+        self.update_fps();
+
+        let mut output = String::new();
+
+        // Draw over the previous frame instead of scrolling
+        if self.lines_drawn > 0 {
+            output.push_str(&format!("\x1b[{}F", self.lines_drawn));
+        }
+
+        let rows = layout(data, self.width, self.layout);
+        for row in &rows {
+            for c in row {
+                output.push_str(&"█".truecolor(c.0, c.1, c.2).to_string());
+            }
+            output.push_str("\x1b[K\n");
+        }
+        self.lines_drawn = rows.len();
+
+        if self.show_fps {
+            output.push_str(&format!("{} leds, {:.1} fps\x1b[K\n", data.len(), self.fps));
+            self.lines_drawn += 1;
+        }
+
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(output.as_bytes())?;
+        stdout.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Splits a frame into rows no wider than `width`
+fn layout(data: &[Color], width: usize, layout: PreviewLayout) -> Vec<Vec<Color>> {
+    match layout {
+        PreviewLayout::Wrap => data.chunks(width).map(|row| row.to_vec()).collect(),
+        PreviewLayout::Downsample if data.len() <= width => vec![data.to_vec()],
+        PreviewLayout::Downsample => {
+            let row = (0..width)
+                .map(|column| {
+                    let start = column * data.len() / width;
+                    let end = (column + 1) * data.len() / width;
+                    average(&data[start..end])
+                })
+                .collect();
+            vec![row]
+        }
+    }
+}
+
+fn average(pixels: &[Color]) -> Color {
+    let (r, g, b) = pixels.iter().fold((0, 0, 0), |(r, g, b), c| {
+        (r + c.0 as usize, g + c.1 as usize, b + c.2 as usize)
+    });
+    let count = pixels.len().max(1);
+
+    Color::new((r / count) as u8, (g / count) as u8, (b / count) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_long_strips() {
+        let rows = layout(&[Color::RED; 25], 10, PreviewLayout::Wrap);
+
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[2].len(), 5);
+    }
+
+    #[test]
+    fn downsamples_long_strips() {
+        let halves: Vec<Color> = [Color::RED, Color::BLUE]
+            .iter()
+            .flat_map(|&c| [c; 10])
+            .collect();
+
+        let rows = layout(&halves, 2, PreviewLayout::Downsample);
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!((rows[0][0].0, rows[0][0].2), (255, 0));
+        assert_eq!((rows[0][1].0, rows[0][1].2), (0, 255));
+
+        let alternating: Vec<Color> = [Color::RED, Color::BLUE].repeat(10);
+
+        let rows = layout(&alternating, 10, PreviewLayout::Downsample);
+
+        assert_eq!(rows[0].len(), 10);
+        assert_eq!((rows[0][4].0, rows[0][4].2), (127, 127));
+    }
+
+    #[test]
+    fn short_strips_are_not_stretched() {
+        let rows = layout(&[Color::RED; 5], 10, PreviewLayout::Downsample);
+        assert_eq!(rows[0].len(), 5);
+    }
+}
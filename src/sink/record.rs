@@ -0,0 +1,32 @@
+use super::{Sink, SinkError};
+use crate::{config::RecordConfig, recording::Recorder, LedData};
+
+/// Records every frame to a file so it can be replayed later. Frames are recorded before
+/// brightness and correction, the Replay effect is processed like any other effect
+pub struct RecordSink {
+    recorder: Recorder,
+    config: RecordConfig,
+}
+
+impl RecordSink {
+    pub fn new(config: &RecordConfig) -> Result<Self, SinkError> {
+        Ok(Self {
+            recorder: Recorder::create(&config.path)?,
+            config: config.clone(),
+        })
+    }
+}
+
+impl Sink for RecordSink {
+    fn name(&self) -> String {
+        format!("recording {}", self.config.path.display())
+    }
+
+    fn send(&mut self, data: &LedData) -> Result<(), SinkError> {
+        Ok(self.recorder.record(data)?)
+    }
+
+    fn before_processing(&self) -> bool {
+        true
+    }
+}
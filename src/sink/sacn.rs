@@ -0,0 +1,62 @@
+use std::iter;
+
+use sacn_unofficial::source::SacnSource;
+
+use super::{check_length, Sink, SinkError};
+use crate::{config::SacnConfig, led_count, LedData};
+
+pub struct SacnSink {
+    src: SacnSource,
+    config: SacnConfig,
+    universes: Vec<u16>,
+}
+
+impl SacnSink {
+    pub fn new(config: &SacnConfig) -> Result<Self, SinkError> {
+        let universes = config.universes(led_count());
+
+        let mut src = SacnSource::with_ip(&config.source_name, config.bind)
+            .map_err(|err| SinkError::Sacn(err.to_string()))?;
+        src.register_universes(&universes)
+            .map_err(|err| SinkError::Sacn(err.to_string()))?;
+
+        Ok(Self {
+            src,
+            config: config.clone(),
+            universes,
+        })
+    }
+}
+
+impl Sink for SacnSink {
+    fn name(&self) -> String {
+        format!("sACN {}", self.config.destination())
+    }
+
+    fn send(&mut self, data: &LedData) -> Result<(), SinkError> {
+        check_length(data, led_count())?;
+
+        for (universe, pixels) in self
+            .universes
+            .iter()
+            .zip(data.chunks(self.config.channel_order.pixels_per_universe()))
+        {
+            // Every universe starts with the DMX null start code
+            let data = iter::once(0)
+                .chain(self.config.channel_order.to_channels(pixels))
+                .collect::<Vec<u8>>();
+
+            self.src
+                .send(
+                    &[*universe],
+                    &data,
+                    Some(self.config.priority),
+                    Some(self.config.destination()),
+                    None,
+                )
+                .map_err(|err| SinkError::Sacn(err.to_string()))?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+use std::net::UdpSocket;
+
+use super::{check_length, Sink, SinkError};
+use crate::{
+    config::{WledConfig, WledProtocol},
+    led_count, Color, LedData,
+};
+
+const WARLS: u8 = 1;
+const DRGB: u8 = 2;
+const DNRGB: u8 = 4;
+
+/// The most leds WLED accepts in a single DNRGB packet
+const DNRGB_LEDS_PER_PACKET: usize = 489;
+
+const DDP_VERSION_1: u8 = 0x40;
+const DDP_PUSH: u8 = 0x01;
+const DDP_TYPE_RGB24: u8 = 0x0B;
+const DDP_DEFAULT_OUTPUT: u8 = 0x01;
+/// The most leds in a single DDP packet, keeps packets within a standard MTU
+const DDP_LEDS_PER_PACKET: usize = 480;
+
+pub struct WledSink {
+    socket: UdpSocket,
+    config: WledConfig,
+    sequence: u8,
+}
+
+impl WledSink {
+    pub fn new(config: &WledConfig) -> Result<Self, SinkError> {
+        Ok(Self {
+            socket: UdpSocket::bind(config.bind)?,
+            config: config.clone(),
+            sequence: 0,
+        })
+    }
+}
+
+impl Sink for WledSink {
+    fn name(&self) -> String {
+        format!("WLED {}", self.config.destination())
+    }
+
+    fn send(&mut self, data: &LedData) -> Result<(), SinkError> {
+        check_length(data, led_count())?;
+
+        // DDP sequence numbers run from 1 to 15, 0 means unused
+        self.sequence = self.sequence % 15 + 1;
+
+        for packet in encode(
+            self.config.protocol,
+            self.config.timeout,
+            self.sequence,
+            data,
+        ) {
+            self.socket.send_to(&packet, self.config.destination())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Encodes a frame into the packets for `protocol`
+fn encode(protocol: WledProtocol, timeout: u8, sequence: u8, data: &[Color]) -> Vec<Vec<u8>> {
+    match protocol {
+        WledProtocol::Warls => {
+            let mut packet = vec![WARLS, timeout];
+            for (index, c) in data.iter().enumerate() {
+                packet.extend_from_slice(&[index as u8, c.0, c.1, c.2]);
+            }
+            vec![packet]
+        }
+        WledProtocol::Drgb => {
+            let mut packet = vec![DRGB, timeout];
+            packet.extend(rgb(data));
+            vec![packet]
+        }
+        WledProtocol::Dnrgb => data
+            .chunks(DNRGB_LEDS_PER_PACKET)
+            .enumerate()
+            .map(|(i, pixels)| {
+                let start = (i * DNRGB_LEDS_PER_PACKET) as u16;
+                let mut packet = vec![DNRGB, timeout];
+                packet.extend_from_slice(&start.to_be_bytes());
+                packet.extend(rgb(pixels));
+                packet
+            })
+            .collect(),
+        WledProtocol::Ddp => {
+            let packets = data.len().div_ceil(DDP_LEDS_PER_PACKET);
+            data.chunks(DDP_LEDS_PER_PACKET)
+                .enumerate()
+                .map(|(i, pixels)| {
+                    // Only the last packet tells the node to display the frame
+                    let flags = if i + 1 == packets {
+                        DDP_VERSION_1 | DDP_PUSH
+                    } else {
+                        DDP_VERSION_1
+                    };
+                    let offset = (i * DDP_LEDS_PER_PACKET * 3) as u32;
+                    let length = (pixels.len() * 3) as u16;
+
+                    let mut packet = vec![flags, sequence, DDP_TYPE_RGB24, DDP_DEFAULT_OUTPUT];
+                    packet.extend_from_slice(&offset.to_be_bytes());
+                    packet.extend_from_slice(&length.to_be_bytes());
+                    packet.extend(rgb(pixels));
+                    packet
+                })
+                .collect()
+        }
+    }
+}
+
+/// WLED applies the strip's colour order itself, so it is always sent RGB
+fn rgb(pixels: &[Color]) -> impl Iterator<Item = u8> + '_ {
+    pixels.iter().flat_map(|c| [c.0, c.1, c.2])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::Ipv4Addr, time::Duration};
+
+    use super::*;
+
+    #[test]
+    fn encodes_warls() {
+        let packets = encode(WledProtocol::Warls, 5, 1, &[Color::RED, Color::BLUE]);
+        assert_eq!(packets, vec![vec![1, 5, 0, 255, 0, 0, 1, 0, 0, 255]]);
+    }
+
+    #[test]
+    fn encodes_drgb() {
+        let packets = encode(WledProtocol::Drgb, 255, 1, &[Color::GREEN]);
+        assert_eq!(packets, vec![vec![2, 255, 0, 255, 0]]);
+    }
+
+    #[test]
+    fn chunks_dnrgb() {
+        let packets = encode(WledProtocol::Dnrgb, 2, 1, &[Color::WHITE; 600]);
+
+        assert_eq!(packets.len(), 2);
+        assert_eq!(&packets[0][..4], &[4, 2, 0, 0]);
+        assert_eq!(packets[0].len(), 4 + 489 * 3);
+        assert_eq!(&packets[1][..4], &[4, 2, 0x01, 0xE9]);
+        assert_eq!(packets[1].len(), 4 + 111 * 3);
+    }
+
+    #[test]
+    fn chunks_ddp() {
+        let packets = encode(WledProtocol::Ddp, 2, 3, &[Color::WHITE; 600]);
+
+        assert_eq!(packets.len(), 2);
+        assert_eq!(
+            &packets[0][..10],
+            &[0x40, 3, 0x0B, 1, 0, 0, 0, 0, 0x05, 0xA0]
+        );
+        assert_eq!(
+            &packets[1][..10],
+            &[0x41, 3, 0x0B, 1, 0, 0, 0x05, 0xA0, 0x01, 0x68]
+        );
+        assert_eq!(packets[1].len(), 10 + 120 * 3);
+    }
+
+    #[test]
+    fn sends_to_local_listener() {
+        let listener = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        listener
+            .set_read_timeout(Some(Duration::from_secs(1)))
+            .unwrap();
+
+        let config = WledConfig {
+            destination: Ipv4Addr::LOCALHOST.into(),
+            port: Some(listener.local_addr().unwrap().port()),
+            protocol: WledProtocol::Drgb,
+            ..WledConfig::default()
+        };
+        let mut sink = WledSink::new(&config).unwrap();
+
+        sink.send(&vec![Color::RED; led_count()]).unwrap();
+
+        let mut buf = [0; 1024];
+        let len = listener.recv(&mut buf).unwrap();
+        assert_eq!(len, 2 + led_count() * 3);
+        assert_eq!(&buf[..5], &[DRGB, 2, 255, 0, 0]);
+    }
+}
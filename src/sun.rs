@@ -0,0 +1,95 @@
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// Julian date of the unix epoch
+const UNIX_EPOCH_JD: f64 = 2_440_587.5;
+/// Julian date of 2000-01-01 12:00 UTC
+const J2000: f64 = 2_451_545.0;
+/// The sun's centre is this far below the horizon at sunrise and sunset, allowing for refraction
+/// and the size of its disc
+const HORIZON: f64 = -0.833;
+/// Tilt of the earth's axis
+const OBLIQUITY: f64 = 23.4397;
+
+/// Sunrise and sunset on `date` at the given coordinates, in degrees with north and east
+/// positive, using the sunrise equation. None when the sun does not rise or set that day
+pub fn sunrise_sunset(
+    date: NaiveDate,
+    latitude: f64,
+    longitude: f64,
+) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    // Days since J2000 to the noon that follows the start of the date
+    let midnight = date.and_hms_opt(0, 0, 0)?.and_utc().timestamp() as f64;
+    let day = (midnight / 86_400.0 + UNIX_EPOCH_JD - J2000 + 0.0008).ceil();
+
+    // Mean solar time, then the sun's mean anomaly, centre and ecliptic longitude
+    let mean_time = day - longitude / 360.0;
+    let anomaly = (357.5291 + 0.985_600_28 * mean_time).rem_euclid(360.0);
+    let m = anomaly.to_radians();
+    let centre = 1.9148 * m.sin() + 0.02 * (2.0 * m).sin() + 0.0003 * (3.0 * m).sin();
+    let ecliptic = (anomaly + centre + 180.0 + 102.9372)
+        .rem_euclid(360.0)
+        .to_radians();
+
+    let transit = J2000 + mean_time + 0.0053 * m.sin() - 0.0069 * (2.0 * ecliptic).sin();
+    let declination = (ecliptic.sin() * OBLIQUITY.to_radians().sin()).asin();
+
+    let latitude = latitude.to_radians();
+    let cos_hour_angle = (HORIZON.to_radians().sin() - latitude.sin() * declination.sin())
+        / (latitude.cos() * declination.cos());
+    if !(-1.0..=1.0).contains(&cos_hour_angle) {
+        return None;
+    }
+    let hour_angle = cos_hour_angle.acos().to_degrees();
+
+    let to_utc = |jd: f64| DateTime::from_timestamp(((jd - UNIX_EPOCH_JD) * 86_400.0) as i64, 0);
+    Some((
+        to_utc(transit - hour_angle / 360.0)?,
+        to_utc(transit + hour_angle / 360.0)?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn assert_near(actual: DateTime<Utc>, expected: DateTime<Utc>) {
+        let error = (actual - expected).num_seconds().abs();
+        assert!(error < 120, "{} is not close to {}", actual, expected);
+    }
+
+    #[test]
+    fn computes_london_midsummer() {
+        let date = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        let (sunrise, sunset) = sunrise_sunset(date, 51.5074, -0.1278).unwrap();
+
+        assert_near(
+            sunrise,
+            Utc.with_ymd_and_hms(2024, 6, 21, 3, 43, 0).unwrap(),
+        );
+        assert_near(
+            sunset,
+            Utc.with_ymd_and_hms(2024, 6, 21, 20, 21, 0).unwrap(),
+        );
+    }
+
+    #[test]
+    fn computes_sydney_midwinter() {
+        let date = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        let (sunrise, sunset) = sunrise_sunset(date, -33.8688, 151.2093).unwrap();
+
+        // 07:00 and 16:54 local time, UTC+10
+        assert_near(
+            sunrise,
+            Utc.with_ymd_and_hms(2024, 6, 20, 21, 0, 0).unwrap(),
+        );
+        assert_near(sunset, Utc.with_ymd_and_hms(2024, 6, 21, 6, 54, 0).unwrap());
+    }
+
+    #[test]
+    fn polar_day_has_no_sunset() {
+        let date = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        assert!(sunrise_sunset(date, 78.2232, 15.6267).is_none());
+    }
+}
@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+use strum_macros::{Display, EnumString};
+
+use crate::{Color, LedData};
+
+/// How the outgoing effect is replaced by the incoming one
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Display, EnumString)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case", ascii_case_insensitive)]
+pub enum TransitionKind {
+    /// Switches straight away
+    Cut,
+    /// Blends the two effects together
+    Crossfade,
+    /// The incoming effect sweeps along the strip from the first led
+    Wipe,
+    /// Fades the outgoing effect out and then the incoming effect in
+    FadeThroughBlack,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transition {
+    pub kind: TransitionKind,
+    pub duration: Duration,
+}
+
+impl Transition {
+    /// Mixes the two frames, `progress` runs from 0.0 (all `from`) to 1.0 (all `to`)
+    pub fn blend(&self, progress: f32, from: &LedData, to: &LedData) -> LedData {
+        let progress = progress.clamp(0.0, 1.0);
+
+        match self.kind {
+            TransitionKind::Cut => to.clone(),
+            TransitionKind::Crossfade => from
+                .iter()
+                .zip(to)
+                .map(|(from, to)| lerp(*from, *to, progress))
+                .collect(),
+            TransitionKind::Wipe => {
+                let edge = (progress * to.len() as f32).round() as usize;
+                to[..edge].iter().chain(&from[edge..]).copied().collect()
+            }
+            TransitionKind::FadeThroughBlack => {
+                let (frame, brightness) = if progress < 0.5 {
+                    (from, 1.0 - progress * 2.0)
+                } else {
+                    (to, progress * 2.0 - 1.0)
+                };
+
+                frame
+                    .iter()
+                    .map(|&c| {
+                        let mut c = c;
+                        c.brightness(brightness);
+                        c
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+fn lerp(from: Color, to: Color, progress: f32) -> Color {
+    let channel =
+        |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * progress).round() as u8;
+
+    Color::new(
+        channel(from.0, to.0),
+        channel(from.1, to.1),
+        channel(from.2, to.2),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transition(kind: TransitionKind) -> Transition {
+        Transition {
+            kind,
+            duration: Duration::from_secs(1),
+        }
+    }
+
+    fn red(data: &LedData) -> Vec<u8> {
+        data.iter().map(|c| c.0).collect()
+    }
+
+    #[test]
+    fn crossfades() {
+        let from = vec![Color::BLACK; 2];
+        let to = vec![Color::RED; 2];
+        let crossfade = transition(TransitionKind::Crossfade);
+
+        assert_eq!(red(&crossfade.blend(0.0, &from, &to)), vec![0, 0]);
+        assert_eq!(red(&crossfade.blend(0.5, &from, &to)), vec![128, 128]);
+        assert_eq!(red(&crossfade.blend(1.0, &from, &to)), vec![255, 255]);
+    }
+
+    #[test]
+    fn wipes_from_the_start() {
+        let from = vec![Color::BLACK; 4];
+        let to = vec![Color::RED; 4];
+
+        let data = transition(TransitionKind::Wipe).blend(0.5, &from, &to);
+
+        assert_eq!(red(&data), vec![255, 255, 0, 0]);
+    }
+
+    #[test]
+    fn fades_through_black() {
+        let from = vec![Color::RED; 1];
+        let to = vec![Color::WHITE; 1];
+        let fade = transition(TransitionKind::FadeThroughBlack);
+
+        assert_eq!(red(&fade.blend(0.25, &from, &to)), vec![127]);
+        assert_eq!(red(&fade.blend(0.5, &from, &to)), vec![0]);
+        assert_eq!(fade.blend(1.0, &from, &to)[0].1, 255);
+    }
+
+    #[test]
+    fn parses_kinds() {
+        assert_eq!(
+            "fade_through_black".parse::<TransitionKind>().unwrap(),
+            TransitionKind::FadeThroughBlack
+        );
+        assert_eq!(
+            "Crossfade".parse::<TransitionKind>().unwrap(),
+            TransitionKind::Crossfade
+        );
+        assert!("dissolve".parse::<TransitionKind>().is_err());
+    }
+}